@@ -0,0 +1,498 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock};
+
+use jni::objects::{GlobalRef, JObject, JString, JValue};
+use jni::sys::{jint, jlong, jobject};
+use jni::{JNIEnv, JavaVM};
+
+use crate::attr_utils::new_long;
+use crate::error::{NfscrsJniError, handle_error};
+use crate::file_ops::{close_file, open_file, read_file, write_file};
+use crate::file_utils::int_to_open_options;
+use crate::opened_file::{file_handle, release_file_handle};
+use crate::session::{SessionHandle, session_handle};
+use crate::{list_dir, read_attr};
+
+/// Worker threads shared by all async calls. Jobs are queued per session
+/// and a session runs one job at a time, so a session whose server hangs
+/// ties up a single worker and the others keep serving other sessions.
+const WORKER_COUNT: usize = 4;
+
+/// Local references a job may hold at once; the frame is popped after each
+/// job, so workers do not accumulate references.
+const JOB_LOCAL_FRAME: i32 = 32;
+
+type JobFn = Box<dyn FnOnce(&mut JNIEnv) -> Result<jobject, NfscrsJniError> + Send>;
+
+/// Releases what a job created when its result is not taken, e.g. closes a
+/// file opened for a future that was cancelled meanwhile.
+type DiscardFn = Box<dyn FnOnce(&mut JNIEnv, &JObject) + Send>;
+
+struct Job {
+    name: &'static str,
+    /// Completed with `complete(Object)` or `completeExceptionally(Throwable)`,
+    /// as on a `CompletableFuture`.
+    callback: GlobalRef,
+    run: JobFn,
+    on_discard: Option<DiscardFn>,
+}
+
+#[derive(Default)]
+struct PoolState {
+    /// Pending jobs per session (see `SessionHandle::session_key`). A
+    /// session has an entry while it has pending jobs or one running.
+    queues: HashMap<usize, VecDeque<Job>>,
+    /// Sessions with pending jobs and none running, in arrival order.
+    ready: VecDeque<usize>,
+    /// Workers that are starting or waiting for jobs.
+    live_workers: usize,
+}
+
+struct WorkerPool {
+    state: Mutex<PoolState>,
+    job_ready: Condvar,
+}
+
+static POOL: OnceLock<WorkerPool> = OnceLock::new();
+
+impl WorkerPool {
+    /// Counts its workers as live from the start, so jobs can be queued
+    /// before they have attached.
+    fn new() -> WorkerPool {
+        WorkerPool {
+            state: Mutex::new(PoolState {
+                live_workers: WORKER_COUNT,
+                ..PoolState::default()
+            }),
+            job_ready: Condvar::new(),
+        }
+    }
+
+    fn spawn_workers(&'static self, vm: JavaVM) {
+        let vm = Arc::new(vm);
+        for i in 0..WORKER_COUNT {
+            let vm = vm.clone();
+            let spawned = std::thread::Builder::new()
+                .name(format!("nfscrs-async-{i}"))
+                .spawn(move || worker_loop(self, &vm));
+            if let Err(e) = spawned {
+                tracing::error!("async pool: failed to start worker {i}: {e}");
+                self.worker_gone();
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, PoolState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn push(&self, session_key: usize, job: Job) -> Result<(), NfscrsJniError> {
+        let mut state = self.lock();
+        if state.live_workers == 0 {
+            return Err(NfscrsJniError::NFSCRSJNIError(
+                "async worker pool has no live workers".to_string(),
+            ));
+        }
+        match state.queues.get_mut(&session_key) {
+            // Already ready, or made ready again when its running job ends.
+            Some(queue) => queue.push_back(job),
+            None => {
+                state.queues.insert(session_key, VecDeque::from([job]));
+                state.ready.push_back(session_key);
+                self.job_ready.notify_one();
+            }
+        }
+        Ok(())
+    }
+
+    /// Waits for the next job of a session that has none running.
+    fn next(&self) -> (usize, Job) {
+        let mut state = self.lock();
+        loop {
+            if let Some(session_key) = state.ready.pop_front()
+                && let Some(job) = state
+                    .queues
+                    .get_mut(&session_key)
+                    .and_then(VecDeque::pop_front)
+            {
+                return (session_key, job);
+            }
+            state = self
+                .job_ready
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// Lets the next job of `session_key` run, if any.
+    fn finish(&self, session_key: usize) {
+        let mut state = self.lock();
+        let has_more = state
+            .queues
+            .get(&session_key)
+            .is_some_and(|queue| !queue.is_empty());
+        if has_more {
+            state.ready.push_back(session_key);
+            self.job_ready.notify_one();
+        } else {
+            state.queues.remove(&session_key);
+        }
+    }
+
+    /// A worker failed to start or attach, or its thread ended. Once none
+    /// are left, `submit` fails and the queued jobs, which nobody would
+    /// run, are dropped.
+    fn worker_gone(&self) {
+        let mut state = self.lock();
+        state.live_workers -= 1;
+        if state.live_workers == 0 {
+            tracing::error!("async pool: no live workers left, dropping queued jobs");
+            state.queues.clear();
+            state.ready.clear();
+        }
+    }
+}
+
+/// Calls `WorkerPool::worker_gone` when a worker thread ends, including by
+/// a panic.
+struct LiveWorker(&'static WorkerPool);
+
+impl Drop for LiveWorker {
+    fn drop(&mut self) {
+        self.0.worker_gone();
+    }
+}
+
+/// Calls `WorkerPool::finish` after a job, including one that panicked, so
+/// its session is not left waiting.
+struct RunningJob(&'static WorkerPool, usize);
+
+impl Drop for RunningJob {
+    fn drop(&mut self) {
+        self.0.finish(self.1);
+    }
+}
+
+fn worker_loop(pool: &'static WorkerPool, vm: &JavaVM) {
+    let _live = LiveWorker(pool);
+    // Daemon threads, so that idle workers never keep the JVM from exiting.
+    let mut env = match vm.attach_current_thread_as_daemon() {
+        Ok(env) => env,
+        Err(e) => {
+            tracing::error!("async pool: failed to attach worker: {e}");
+            return;
+        }
+    };
+    loop {
+        let (session_key, job) = pool.next();
+        let _running = RunningJob(pool, session_key);
+        let name = job.name;
+        let result = env.with_local_frame(JOB_LOCAL_FRAME, |env| -> Result<(), NfscrsJniError> {
+            run_job(env, job);
+            Ok(())
+        });
+        if let Err(e) = result {
+            tracing::error!("async {name}: {e:?}");
+        }
+    }
+}
+
+/// Cancellation is cooperative: a job whose future is already done (e.g.
+/// cancelled with `CompletableFuture.cancel`) is skipped, and a reply that
+/// arrives after cancellation is discarded by `complete` returning false.
+/// The RPCs themselves always run to completion, which keeps the session's
+/// slot and sequence ids in step with the server.
+fn run_job(env: &mut JNIEnv, job: Job) {
+    match env
+        .call_method(&job.callback, "isDone", "()Z", &[])
+        .and_then(|done| done.z())
+    {
+        Ok(true) => {
+            tracing::debug!("async {}: cancelled before start", job.name);
+            return;
+        }
+        Ok(false) => {}
+        // Not a CompletableFuture; run the job regardless.
+        Err(_) => {
+            let _ = env.exception_clear();
+        }
+    }
+    tracing::debug!("async {}: start", job.name);
+    let result = (job.run)(env);
+    let completed = match result {
+        Ok(value) => {
+            let value = unsafe { JObject::from_raw(value) };
+            let completed = env.call_method(
+                &job.callback,
+                "complete",
+                "(Ljava/lang/Object;)Z",
+                &[JValue::Object(&value)],
+            );
+            if matches!(completed.as_ref().map(|taken| taken.z()), Ok(Ok(false))) {
+                tracing::debug!("async {}: cancelled, discarding the reply", job.name);
+                if let Some(on_discard) = job.on_discard {
+                    on_discard(env, &value);
+                }
+            }
+            completed
+        }
+        Err(e) => {
+            tracing::debug!("async {}: failed: {e:?}", job.name);
+            handle_error(env, &e);
+            let throwable = match env.exception_occurred() {
+                Ok(throwable) if !throwable.is_null() => throwable,
+                _ => {
+                    tracing::error!("async {}: no exception to complete with", job.name);
+                    return;
+                }
+            };
+            let _ = env.exception_clear();
+            env.call_method(
+                &job.callback,
+                "completeExceptionally",
+                "(Ljava/lang/Throwable;)Z",
+                &[JValue::Object(&throwable)],
+            )
+        }
+    };
+    // Exceptions thrown by the callback have nobody to go to on this thread.
+    if completed.is_err() && env.exception_check().unwrap_or(false) {
+        let _ = env.exception_describe();
+        let _ = env.exception_clear();
+    }
+}
+
+/// Queues `run` on the worker pool behind earlier jobs of the same session,
+/// to complete `callback` with its result. Errors here are thrown to the
+/// caller, since the callback never runs.
+fn submit(
+    env: &mut JNIEnv,
+    session_handle: &SessionHandle,
+    name: &'static str,
+    callback: &JObject,
+    run: JobFn,
+    on_discard: Option<DiscardFn>,
+) -> Result<(), NfscrsJniError> {
+    if callback.is_null() {
+        return Err(NfscrsJniError::IllegalArgument(
+            "callback must not be null".to_string(),
+        ));
+    }
+    let callback = env.new_global_ref(callback)?;
+    let pool = match POOL.get() {
+        Some(pool) => pool,
+        None => {
+            let vm = env.get_java_vm()?;
+            let mut created = false;
+            let pool = POOL.get_or_init(|| {
+                created = true;
+                WorkerPool::new()
+            });
+            if created {
+                pool.spawn_workers(vm);
+            }
+            pool
+        }
+    };
+    pool.push(
+        session_handle.session_key(),
+        Job {
+            name,
+            callback,
+            run,
+            on_discard,
+        },
+    )
+}
+
+/// Global reference to an argument the job reads on a worker thread, since
+/// the caller's local references die when the native method returns.
+fn pin_argument(env: &mut JNIEnv, obj: &JObject) -> Result<GlobalRef, NfscrsJniError> {
+    Ok(env.new_global_ref(obj)?)
+}
+
+/// Async `fileRead`: `callback` completes with an `NFS4FileReadResult`.
+/// The buffer must not be touched until then.
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_fileReadAsync(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    opened_file: jlong,
+    offset: jlong,
+    byte_buffer: JObject,
+    callback: JObject,
+) {
+    let session_handle = unsafe { session_handle(session) };
+    let file_handle = unsafe { file_handle(opened_file) };
+    let result = pin_argument(&mut env, &byte_buffer).and_then(|byte_buffer| {
+        let run: JobFn = Box::new(move |env| {
+            read_file(
+                session_handle,
+                &file_handle,
+                byte_buffer.as_obj(),
+                offset as usize,
+                env,
+            )
+        });
+        submit(&mut env, session_handle, "fileRead", &callback, run, None)
+    });
+    if let Err(e) = result {
+        handle_error(&mut env, &e);
+    }
+}
+
+/// Async `fileWrite`: `callback` completes with an `NFS4FileWriteResult`.
+/// The buffer must not be touched until then.
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_fileWriteAsync(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    opened_file: jlong,
+    offset: jlong,
+    byte_buffer: JObject,
+    callback: JObject,
+) {
+    let session_handle = unsafe { session_handle(session) };
+    let file_handle = unsafe { file_handle(opened_file) };
+    let result = pin_argument(&mut env, &byte_buffer).and_then(|byte_buffer| {
+        let run: JobFn = Box::new(move |env| {
+            write_file(
+                session_handle,
+                &file_handle,
+                byte_buffer.as_obj(),
+                offset as usize,
+                env,
+            )
+        });
+        submit(&mut env, session_handle, "fileWrite", &callback, run, None)
+    });
+    if let Err(e) = result {
+        handle_error(&mut env, &e);
+    }
+}
+
+/// Async `listDir`: `callback` completes with an `ArrayList<String>`.
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_listDirAsync(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    path: JString,
+    callback: JObject,
+) {
+    let session_handle = unsafe { session_handle(session) };
+    let result = pin_argument(&mut env, &path).and_then(|path| {
+        let run: JobFn =
+            Box::new(move |env| list_dir(session_handle, env, <&JString>::from(path.as_obj())));
+        submit(&mut env, session_handle, "listDir", &callback, run, None)
+    });
+    if let Err(e) = result {
+        handle_error(&mut env, &e);
+    }
+}
+
+/// Async `readAttr`: `callback` completes with an `NFS4FileAttributes`.
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_readAttrAsync(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    path: JString,
+    callback: JObject,
+) {
+    let session_handle = unsafe { session_handle(session) };
+    let result = pin_argument(&mut env, &path).and_then(|path| {
+        let run: JobFn =
+            Box::new(move |env| read_attr(session_handle, env, <&JString>::from(path.as_obj())));
+        submit(&mut env, session_handle, "readAttr", &callback, run, None)
+    });
+    if let Err(e) = result {
+        handle_error(&mut env, &e);
+    }
+}
+
+/// Async `openFile`: `callback` completes with the file handle as a `Long`.
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_openFileAsync(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    path: JString,
+    open_options: jint,
+    callback: JObject,
+) {
+    let session_handle = unsafe { session_handle(session) };
+    let opts = int_to_open_options(open_options);
+    let result = pin_argument(&mut env, &path).and_then(|path| {
+        let run: JobFn = Box::new(move |env| {
+            let path = <&JString>::from(path.as_obj());
+            let file_ptr = open_file(session_handle, env, path, opts)?;
+            Ok(new_long(file_ptr, env)?.into_raw())
+        });
+        let on_discard: DiscardFn = Box::new(move |env, value| {
+            if let Err(e) = close_discarded(session_handle, env, value) {
+                tracing::error!("async openFile: failed to close discarded file: {e:?}");
+            }
+        });
+        submit(
+            &mut env,
+            session_handle,
+            "openFile",
+            &callback,
+            run,
+            Some(on_discard),
+        )
+    });
+    if let Err(e) = result {
+        handle_error(&mut env, &e);
+    }
+}
+
+fn close_discarded(
+    session_handle: &'static SessionHandle,
+    env: &mut JNIEnv,
+    value: &JObject, // Long
+) -> Result<(), NfscrsJniError> {
+    let opened_file = env.call_method(value, "longValue", "()J", &[])?.j()?;
+    let file_handle = unsafe { file_handle(opened_file) };
+    close_file(env, session_handle, &file_handle)?;
+    drop(file_handle);
+    unsafe {
+        release_file_handle(opened_file); // release opened file
+    }
+    Ok(())
+}
+
+/// Async `fileClose`: `callback` completes with null once the file is
+/// closed. As with `fileClose`, the handle stays valid when closing fails,
+/// and also when the future is cancelled before the close starts.
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_fileCloseAsync(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    opened_file: jlong,
+    callback: JObject,
+) {
+    let session_handle = unsafe { session_handle(session) };
+    let file_handle = unsafe { file_handle(opened_file) };
+    let run: JobFn = Box::new(move |env| {
+        close_file(env, session_handle, &file_handle)?;
+        drop(file_handle);
+        unsafe {
+            release_file_handle(opened_file); // release opened file
+        }
+        Ok(std::ptr::null_mut())
+    });
+    if let Err(e) = submit(&mut env, session_handle, "fileClose", &callback, run, None) {
+        handle_error(&mut env, &e);
+    }
+}
@@ -1,10 +1,16 @@
 use jni::JNIEnv;
+use jni::objects::{JClass, JObject, JStaticMethodID, JValue};
+use nfscrs::NFSClientSession;
+use nfscrs::nfs4_utils::nfs4time_to_miliseconds;
+use nfscrs::nfscrs_error::NFSCRSError;
+use nfscrs::nfscrs_types::AbsolutePath;
 use nfscrs::{
-    fattr4::{FAttr4, FAttr4Type, fattr4_names},
-    nfs4_types::{NFSFType4, NFSTime4},
+    fattr4::{FAttr4, FAttr4Type, fattr4_names, set_bitmap},
+    nfs4_types::{BitMap4, NFSFType4, NFSTime4},
 };
 
 use crate::error::NfscrsJniError;
+use crate::jni_utils::{OptionalClass, as_class, jni_cache};
 
 pub fn get_access_time(fattr4: &FAttr4, env: &mut JNIEnv) -> NFSTime4 {
     if let Ok(fattr4type) = fattr4.fetch_attr(fattr4_names::FATTR4_TIME_ACCESS)
@@ -62,6 +68,98 @@ pub fn get_file_size(fattr4: &FAttr4) -> Result<u64, NfscrsJniError> {
     }
 }
 
+/// Bitmap selecting only the change attribute.
+pub fn change_bitmap() -> BitMap4 {
+    let mut bitmap = BitMap4::new();
+    set_bitmap(&mut bitmap, fattr4_names::FATTR4_CHANGE);
+    bitmap
+}
+
+pub fn get_change_attr(fattr4: &FAttr4) -> Option<u64> {
+    match fattr4.fetch_attr(fattr4_names::FATTR4_CHANGE) {
+        Ok(FAttr4Type::FATTR4_CHANGE(change)) => Some(change),
+        _ => None,
+    }
+}
+
+/// Fetches the maxread and maxwrite attributes of the filesystem holding
+/// `path`.
+pub fn get_max_read_write(
+    session_ref: &mut NFSClientSession,
+    path: &AbsolutePath,
+) -> Result<(Option<u64>, Option<u64>), NFSCRSError> {
+    let mut bitmap = BitMap4::new();
+    set_bitmap(&mut bitmap, fattr4_names::FATTR4_MAXREAD);
+    set_bitmap(&mut bitmap, fattr4_names::FATTR4_MAXWRITE);
+    let fattr4 = session_ref.get_attr(path, bitmap)?;
+    let max_read = match fattr4.fetch_attr(fattr4_names::FATTR4_MAXREAD) {
+        Ok(FAttr4Type::FATTR4_MAXREAD(max_read)) => Some(max_read),
+        _ => None,
+    };
+    let max_write = match fattr4.fetch_attr(fattr4_names::FATTR4_MAXWRITE) {
+        Ok(FAttr4Type::FATTR4_MAXWRITE(max_write)) => Some(max_write),
+        _ => None,
+    };
+    Ok((max_read, max_write))
+}
+
+/// Attributes that place `path` in the server's file system tree.
+#[derive(Debug, Clone, Copy)]
+pub struct FsPosition {
+    pub fsid: (u64, u64),
+    pub fileid: Option<u64>,
+    pub mounted_on_fileid: Option<u64>,
+    pub is_dir: bool,
+}
+
+/// The attributes `FsPosition` is made of.
+pub fn fs_position_bitmap() -> BitMap4 {
+    let mut bitmap = BitMap4::new();
+    set_bitmap(&mut bitmap, fattr4_names::FATTR4_TYPE);
+    set_bitmap(&mut bitmap, fattr4_names::FATTR4_FSID);
+    set_bitmap(&mut bitmap, fattr4_names::FATTR4_FILEID);
+    set_bitmap(&mut bitmap, fattr4_names::FATTR4_MOUNTED_ON_FILEID);
+    bitmap
+}
+
+/// Fetches the fsid, fileid, mounted_on_fileid and type of `path`.
+pub fn get_fs_position(
+    session_ref: &mut NFSClientSession,
+    path: &AbsolutePath,
+) -> Result<FsPosition, NfscrsJniError> {
+    let fattr4 = session_ref.get_attr(path, fs_position_bitmap())?;
+    fs_position_from_fattr(&fattr4).ok_or_else(|| {
+        NfscrsJniError::NFSCRSJNIError(format!("server did not return fsid for {path:?}"))
+    })
+}
+
+/// Reads an `FsPosition` from attributes requested with
+/// `fs_position_bitmap`, e.g. by READDIR. `None` without an fsid.
+pub fn fs_position_from_fattr(fattr4: &FAttr4) -> Option<FsPosition> {
+    let fsid = match fattr4.fetch_attr(fattr4_names::FATTR4_FSID) {
+        Ok(FAttr4Type::FATTR4_FSID(fsid)) => (fsid.major, fsid.minor),
+        _ => return None,
+    };
+    let fileid = match fattr4.fetch_attr(fattr4_names::FATTR4_FILEID) {
+        Ok(FAttr4Type::FATTR4_FILEID(fileid)) => Some(fileid),
+        _ => None,
+    };
+    let mounted_on_fileid = match fattr4.fetch_attr(fattr4_names::FATTR4_MOUNTED_ON_FILEID) {
+        Ok(FAttr4Type::FATTR4_MOUNTED_ON_FILEID(fileid)) => Some(fileid),
+        _ => None,
+    };
+    let is_dir = matches!(
+        fattr4.fetch_attr(fattr4_names::FATTR4_TYPE),
+        Ok(FAttr4Type::FATTR4_TYPE(NFSFType4::NF4DIR))
+    );
+    Some(FsPosition {
+        fsid,
+        fileid,
+        mounted_on_fileid,
+        is_dir,
+    })
+}
+
 pub fn get_file_mode(fattr4: &FAttr4, env: &mut JNIEnv) -> u32 {
     if let Ok(fattr4type) = fattr4.fetch_attr(fattr4_names::FATTR4_MODE)
         && let FAttr4Type::FATTR4_MODE(t) = fattr4type
@@ -72,3 +170,463 @@ pub fn get_file_mode(fattr4: &FAttr4, env: &mut JNIEnv) -> u32 {
         unreachable!()
     }
 }
+
+/// An attribute that can be requested by name through `readAttrs`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NamedAttr {
+    LastModifiedTime,
+    LastAccessTime,
+    CreationTime,
+    Size,
+    IsRegularFile,
+    IsDirectory,
+    IsSymbolicLink,
+    IsOther,
+    FileKey,
+    Permissions,
+    /// `UserPrincipal` of the posix view.
+    Owner,
+    /// `GroupPrincipal` of the posix view.
+    Group,
+    /// Owner string of the nfs4 view.
+    OwnerName,
+    /// Owner group string of the nfs4 view.
+    GroupName,
+    Mode,
+    Type,
+    FileId,
+    NumLinks,
+    Change,
+    SpaceUsed,
+    MetadataTime,
+}
+
+const BASIC_ATTRS: &[NamedAttr] = &[
+    NamedAttr::LastModifiedTime,
+    NamedAttr::LastAccessTime,
+    NamedAttr::CreationTime,
+    NamedAttr::Size,
+    NamedAttr::IsRegularFile,
+    NamedAttr::IsDirectory,
+    NamedAttr::IsSymbolicLink,
+    NamedAttr::IsOther,
+    NamedAttr::FileKey,
+];
+
+const POSIX_ATTRS: &[NamedAttr] = &[
+    NamedAttr::LastModifiedTime,
+    NamedAttr::LastAccessTime,
+    NamedAttr::CreationTime,
+    NamedAttr::Size,
+    NamedAttr::IsRegularFile,
+    NamedAttr::IsDirectory,
+    NamedAttr::IsSymbolicLink,
+    NamedAttr::IsOther,
+    NamedAttr::FileKey,
+    NamedAttr::Permissions,
+    NamedAttr::Owner,
+    NamedAttr::Group,
+];
+
+const NFS4_ATTRS: &[NamedAttr] = &[
+    NamedAttr::Size,
+    NamedAttr::Mode,
+    NamedAttr::Type,
+    NamedAttr::FileId,
+    NamedAttr::NumLinks,
+    NamedAttr::Change,
+    NamedAttr::SpaceUsed,
+    NamedAttr::OwnerName,
+    NamedAttr::GroupName,
+    NamedAttr::LastAccessTime,
+    NamedAttr::LastModifiedTime,
+    NamedAttr::CreationTime,
+    NamedAttr::MetadataTime,
+];
+
+impl NamedAttr {
+    /// Key used for this attribute in the map returned to Java.
+    pub fn key(&self, view: &str) -> &'static str {
+        match self {
+            NamedAttr::LastModifiedTime if view == "nfs4" => "time_modify",
+            NamedAttr::LastAccessTime if view == "nfs4" => "time_access",
+            NamedAttr::CreationTime if view == "nfs4" => "time_create",
+            NamedAttr::LastModifiedTime => "lastModifiedTime",
+            NamedAttr::LastAccessTime => "lastAccessTime",
+            NamedAttr::CreationTime => "creationTime",
+            NamedAttr::Size => "size",
+            NamedAttr::IsRegularFile => "isRegularFile",
+            NamedAttr::IsDirectory => "isDirectory",
+            NamedAttr::IsSymbolicLink => "isSymbolicLink",
+            NamedAttr::IsOther => "isOther",
+            NamedAttr::FileKey => "fileKey",
+            NamedAttr::Permissions => "permissions",
+            NamedAttr::Owner => "owner",
+            NamedAttr::Group => "group",
+            NamedAttr::OwnerName => "owner",
+            NamedAttr::GroupName => "owner_group",
+            NamedAttr::Mode => "mode",
+            NamedAttr::Type => "type",
+            NamedAttr::FileId => "fileid",
+            NamedAttr::NumLinks => "numlinks",
+            NamedAttr::Change => "change",
+            NamedAttr::SpaceUsed => "space_used",
+            NamedAttr::MetadataTime => "time_metadata",
+        }
+    }
+
+    /// Sets the fattr4 bits needed to answer this attribute.
+    pub fn set_bits(&self, bitmap: &mut BitMap4) {
+        match self {
+            NamedAttr::LastModifiedTime => set_bitmap(bitmap, fattr4_names::FATTR4_TIME_MODIFY),
+            NamedAttr::LastAccessTime => set_bitmap(bitmap, fattr4_names::FATTR4_TIME_ACCESS),
+            NamedAttr::CreationTime => set_bitmap(bitmap, fattr4_names::FATTR4_TIME_CREATE),
+            NamedAttr::Size => set_bitmap(bitmap, fattr4_names::FATTR4_SIZE),
+            NamedAttr::IsRegularFile
+            | NamedAttr::IsDirectory
+            | NamedAttr::IsSymbolicLink
+            | NamedAttr::IsOther
+            | NamedAttr::Type => set_bitmap(bitmap, fattr4_names::FATTR4_TYPE),
+            NamedAttr::FileKey | NamedAttr::FileId => {
+                set_bitmap(bitmap, fattr4_names::FATTR4_FILEID)
+            }
+            NamedAttr::Permissions | NamedAttr::Mode => {
+                set_bitmap(bitmap, fattr4_names::FATTR4_MODE)
+            }
+            NamedAttr::Owner | NamedAttr::OwnerName => {
+                set_bitmap(bitmap, fattr4_names::FATTR4_OWNER)
+            }
+            NamedAttr::Group | NamedAttr::GroupName => {
+                set_bitmap(bitmap, fattr4_names::FATTR4_OWNER_GROUP)
+            }
+            NamedAttr::NumLinks => set_bitmap(bitmap, fattr4_names::FATTR4_NUMLINKS),
+            NamedAttr::Change => set_bitmap(bitmap, fattr4_names::FATTR4_CHANGE),
+            NamedAttr::SpaceUsed => set_bitmap(bitmap, fattr4_names::FATTR4_SPACE_USED),
+            NamedAttr::MetadataTime => set_bitmap(bitmap, fattr4_names::FATTR4_TIME_METADATA),
+        }
+    }
+}
+
+fn view_attrs(view: &str) -> Option<&'static [NamedAttr]> {
+    match view {
+        "basic" => Some(BASIC_ATTRS),
+        "posix" => Some(POSIX_ATTRS),
+        "nfs4" => Some(NFS4_ATTRS),
+        _ => None,
+    }
+}
+
+/// Parses attribute names in the `Files.readAttributes` form (`view:name`,
+/// `view:*`, or a bare name in the `basic` view) into `(key, attr)` pairs.
+pub fn parse_attr_names(
+    names: &[String],
+) -> Result<Vec<(&'static str, NamedAttr)>, NfscrsJniError> {
+    let mut attrs: Vec<(&'static str, NamedAttr)> = Vec::new();
+    for full_name in names {
+        let (view, names_part) = full_name.split_once(':').unwrap_or(("basic", full_name));
+        let view_set = view_attrs(view).ok_or_else(|| {
+            NfscrsJniError::UnsupportedOperation(format!("view not supported: {view}"))
+        })?;
+        for name in names_part.split(',') {
+            let selected: Vec<NamedAttr> = if name == "*" {
+                view_set.to_vec()
+            } else {
+                let attr = view_set
+                    .iter()
+                    .find(|a| a.key(view) == name)
+                    .ok_or_else(|| {
+                        NfscrsJniError::IllegalArgument(format!(
+                            "'{name}' not recognized in view {view}"
+                        ))
+                    })?;
+                vec![*attr]
+            };
+            for attr in selected {
+                let key = attr.key(view);
+                if !attrs.iter().any(|(k, _)| *k == key) {
+                    attrs.push((key, attr));
+                }
+            }
+        }
+    }
+    Ok(attrs)
+}
+
+/// Renders the permission bits of an NFSv4 mode as `rwxr-x---`.
+pub fn mode_to_permission_string(mode: u32) -> String {
+    const FLAGS: [char; 3] = ['r', 'w', 'x'];
+    (0..9)
+        .map(|i| {
+            if mode & (1 << (8 - i)) != 0 {
+                FLAGS[i % 3]
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// Converts one requested attribute to the Java object stored in the
+/// `readAttrs` result map. Returns `None` when the server did not return it.
+pub fn named_attr_to_java<'a>(
+    fattr4: &FAttr4,
+    attr: NamedAttr,
+    env: &mut JNIEnv<'a>,
+) -> Result<Option<JObject<'a>>, NfscrsJniError> {
+    let filetype = match fattr4.fetch_attr(fattr4_names::FATTR4_TYPE) {
+        Ok(FAttr4Type::FATTR4_TYPE(t)) => Some(t),
+        _ => None,
+    };
+    let value = match attr {
+        NamedAttr::LastModifiedTime => match fattr4.fetch_attr(fattr4_names::FATTR4_TIME_MODIFY) {
+            Ok(FAttr4Type::FATTR4_TIME_MODIFY(t)) => Some(new_filetime(&t, env)?),
+            _ => None,
+        },
+        NamedAttr::LastAccessTime => match fattr4.fetch_attr(fattr4_names::FATTR4_TIME_ACCESS) {
+            Ok(FAttr4Type::FATTR4_TIME_ACCESS(t)) => Some(new_filetime(&t, env)?),
+            _ => None,
+        },
+        NamedAttr::CreationTime => match fattr4.fetch_attr(fattr4_names::FATTR4_TIME_CREATE) {
+            Ok(FAttr4Type::FATTR4_TIME_CREATE(t)) => Some(new_filetime(&t, env)?),
+            _ => None,
+        },
+        NamedAttr::MetadataTime => match fattr4.fetch_attr(fattr4_names::FATTR4_TIME_METADATA) {
+            Ok(FAttr4Type::FATTR4_TIME_METADATA(t)) => Some(new_filetime(&t, env)?),
+            _ => None,
+        },
+        NamedAttr::Size => match fattr4.fetch_attr(fattr4_names::FATTR4_SIZE) {
+            Ok(FAttr4Type::FATTR4_SIZE(t)) => Some(new_long(t as i64, env)?),
+            _ => None,
+        },
+        NamedAttr::SpaceUsed => match fattr4.fetch_attr(fattr4_names::FATTR4_SPACE_USED) {
+            Ok(FAttr4Type::FATTR4_SPACE_USED(t)) => Some(new_long(t as i64, env)?),
+            _ => None,
+        },
+        NamedAttr::Change => match fattr4.fetch_attr(fattr4_names::FATTR4_CHANGE) {
+            Ok(FAttr4Type::FATTR4_CHANGE(t)) => Some(new_long(t as i64, env)?),
+            _ => None,
+        },
+        NamedAttr::FileKey | NamedAttr::FileId => {
+            match fattr4.fetch_attr(fattr4_names::FATTR4_FILEID) {
+                Ok(FAttr4Type::FATTR4_FILEID(t)) => Some(new_long(t as i64, env)?),
+                _ => None,
+            }
+        }
+        NamedAttr::NumLinks => match fattr4.fetch_attr(fattr4_names::FATTR4_NUMLINKS) {
+            Ok(FAttr4Type::FATTR4_NUMLINKS(t)) => Some(new_integer(t as i32, env)?),
+            _ => None,
+        },
+        NamedAttr::Mode => match fattr4.fetch_attr(fattr4_names::FATTR4_MODE) {
+            Ok(FAttr4Type::FATTR4_MODE(t)) => Some(new_integer(t as i32, env)?),
+            _ => None,
+        },
+        NamedAttr::Permissions => match fattr4.fetch_attr(fattr4_names::FATTR4_MODE) {
+            Ok(FAttr4Type::FATTR4_MODE(t)) => {
+                let cache = jni_cache()?;
+                let perms = env.new_string(mode_to_permission_string(t))?;
+                let set = call_static_factory(
+                    env,
+                    as_class(&cache.posix_file_permissions_class),
+                    cache.posix_file_permissions_from_string,
+                    JValue::Object(&perms),
+                )?;
+                env.delete_local_ref(perms)?;
+                Some(set)
+            }
+            _ => None,
+        },
+        NamedAttr::Owner => match fattr4.fetch_attr(fattr4_names::FATTR4_OWNER) {
+            Ok(FAttr4Type::FATTR4_OWNER(t)) => {
+                Some(new_principal(&jni_cache()?.user_principal, &t, env)?)
+            }
+            _ => None,
+        },
+        NamedAttr::Group => match fattr4.fetch_attr(fattr4_names::FATTR4_OWNER_GROUP) {
+            Ok(FAttr4Type::FATTR4_OWNER_GROUP(t)) => {
+                Some(new_principal(&jni_cache()?.group_principal, &t, env)?)
+            }
+            _ => None,
+        },
+        NamedAttr::OwnerName => match fattr4.fetch_attr(fattr4_names::FATTR4_OWNER) {
+            Ok(FAttr4Type::FATTR4_OWNER(t)) => Some(env.new_string(t)?.into()),
+            _ => None,
+        },
+        NamedAttr::GroupName => match fattr4.fetch_attr(fattr4_names::FATTR4_OWNER_GROUP) {
+            Ok(FAttr4Type::FATTR4_OWNER_GROUP(t)) => Some(env.new_string(t)?.into()),
+            _ => None,
+        },
+        NamedAttr::Type => match filetype {
+            Some(t) => Some(env.new_string(filetype_name(&t))?.into()),
+            None => None,
+        },
+        NamedAttr::IsRegularFile => match filetype {
+            Some(t) => Some(new_boolean(matches!(t, NFSFType4::NF4REG), env)?),
+            None => None,
+        },
+        NamedAttr::IsDirectory => match filetype {
+            Some(t) => Some(new_boolean(matches!(t, NFSFType4::NF4DIR), env)?),
+            None => None,
+        },
+        NamedAttr::IsSymbolicLink => match filetype {
+            Some(t) => Some(new_boolean(matches!(t, NFSFType4::NF4LNK), env)?),
+            None => None,
+        },
+        NamedAttr::IsOther => match filetype {
+            Some(t) => Some(new_boolean(
+                !matches!(t, NFSFType4::NF4REG | NFSFType4::NF4DIR | NFSFType4::NF4LNK),
+                env,
+            )?),
+            None => None,
+        },
+    };
+    Ok(value)
+}
+
+fn filetype_name(filetype: &NFSFType4) -> &'static str {
+    match filetype {
+        NFSFType4::NF4REG => "regular",
+        NFSFType4::NF4DIR => "directory",
+        NFSFType4::NF4LNK => "symlink",
+        NFSFType4::NF4BLK => "block",
+        NFSFType4::NF4CHR => "char",
+        NFSFType4::NF4SOCK => "socket",
+        NFSFType4::NF4FIFO => "fifo",
+        _ => "other",
+    }
+}
+
+fn new_filetime<'a>(time: &NFSTime4, env: &mut JNIEnv<'a>) -> Result<JObject<'a>, NfscrsJniError> {
+    let cache = jni_cache()?;
+    call_static_factory(
+        env,
+        as_class(&cache.filetime_class),
+        cache.filetime_from_millis,
+        JValue::Long(nfs4time_to_miliseconds(time)),
+    )
+}
+
+pub fn new_long<'a>(value: i64, env: &mut JNIEnv<'a>) -> Result<JObject<'a>, NfscrsJniError> {
+    let cache = jni_cache()?;
+    call_static_factory(
+        env,
+        as_class(&cache.long_class),
+        cache.long_value_of,
+        JValue::Long(value),
+    )
+}
+
+fn new_integer<'a>(value: i32, env: &mut JNIEnv<'a>) -> Result<JObject<'a>, NfscrsJniError> {
+    let cache = jni_cache()?;
+    call_static_factory(
+        env,
+        as_class(&cache.integer_class),
+        cache.integer_value_of,
+        JValue::Int(value),
+    )
+}
+
+fn new_boolean<'a>(value: bool, env: &mut JNIEnv<'a>) -> Result<JObject<'a>, NfscrsJniError> {
+    let cache = jni_cache()?;
+    call_static_factory(
+        env,
+        as_class(&cache.boolean_class),
+        cache.boolean_value_of,
+        JValue::Bool(value as u8),
+    )
+}
+
+/// Builds an `NFS4UserPrincipal` or `NFS4GroupPrincipal` from the NFSv4
+/// `user@domain` name.
+fn new_principal<'a>(
+    class: &OptionalClass,
+    name: &str,
+    env: &mut JNIEnv<'a>,
+) -> Result<JObject<'a>, NfscrsJniError> {
+    let (class, ctor) = class.get(env)?;
+    let jname = env.new_string(name)?;
+    let principal =
+        unsafe { env.new_object_unchecked(class, ctor, &[JValue::Object(&jname).as_jni()]) }?;
+    env.delete_local_ref(jname)?;
+    Ok(principal)
+}
+
+fn call_static_factory<'a>(
+    env: &mut JNIEnv<'a>,
+    class: &JClass,
+    method: JStaticMethodID,
+    arg: JValue,
+) -> Result<JObject<'a>, NfscrsJniError> {
+    let obj = unsafe {
+        env.call_static_method_unchecked(
+            class,
+            method,
+            jni::signature::ReturnType::Object,
+            &[arg.as_jni()],
+        )
+    }?
+    .l()?;
+    Ok(obj)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permission_string_renders_mode_bits() {
+        assert_eq!(mode_to_permission_string(0o754), "rwxr-xr--");
+        assert_eq!(mode_to_permission_string(0o000), "---------");
+        assert_eq!(mode_to_permission_string(0o777), "rwxrwxrwx");
+        // Type, setuid and sticky bits are not permissions.
+        assert_eq!(mode_to_permission_string(0o104644), "rw-r--r--");
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_bare_names_in_the_basic_view() {
+        let attrs = parse_attr_names(&names(&["size,lastModifiedTime"])).unwrap();
+        assert_eq!(
+            attrs,
+            vec![
+                ("size", NamedAttr::Size),
+                ("lastModifiedTime", NamedAttr::LastModifiedTime),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_view_wildcards_without_duplicates() {
+        let attrs = parse_attr_names(&names(&["posix:*", "basic:size"])).unwrap();
+        assert_eq!(attrs.len(), POSIX_ATTRS.len());
+        assert!(attrs.contains(&("owner", NamedAttr::Owner)));
+        assert!(attrs.contains(&("group", NamedAttr::Group)));
+    }
+
+    #[test]
+    fn nfs4_view_uses_nfs4_names() {
+        let attrs = parse_attr_names(&names(&["nfs4:owner,owner_group,time_modify"])).unwrap();
+        assert_eq!(
+            attrs,
+            vec![
+                ("owner", NamedAttr::OwnerName),
+                ("owner_group", NamedAttr::GroupName),
+                ("time_modify", NamedAttr::LastModifiedTime),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_views_and_names() {
+        assert!(matches!(
+            parse_attr_names(&names(&["dos:hidden"])),
+            Err(NfscrsJniError::UnsupportedOperation(_))
+        ));
+        assert!(matches!(
+            parse_attr_names(&names(&["posix:nlink"])),
+            Err(NfscrsJniError::IllegalArgument(_))
+        ));
+    }
+}
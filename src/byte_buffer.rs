@@ -0,0 +1,183 @@
+use std::borrow::Cow;
+
+use jni::JNIEnv;
+use jni::objects::{JByteArray, JObject, JValue};
+use jni::signature::{Primitive, ReturnType};
+
+use crate::error::NfscrsJniError;
+use crate::jni_utils::jni_cache;
+
+pub fn buffer_position(env: &mut JNIEnv, byte_buffer: &JObject) -> Result<i32, NfscrsJniError> {
+    let cache = jni_cache()?;
+    let position = unsafe {
+        env.call_method_unchecked(
+            byte_buffer,
+            cache.buffer_position,
+            ReturnType::Primitive(Primitive::Int),
+            &[],
+        )
+    }?
+    .i()?;
+    Ok(position)
+}
+
+pub fn set_buffer_position(
+    env: &mut JNIEnv,
+    byte_buffer: &JObject,
+    position: i32,
+) -> Result<(), NfscrsJniError> {
+    let cache = jni_cache()?;
+    let ret = unsafe {
+        env.call_method_unchecked(
+            byte_buffer,
+            cache.buffer_set_position,
+            ReturnType::Object,
+            &[JValue::Int(position).as_jni()],
+        )
+    }?
+    .l()?;
+    env.delete_local_ref(ret)?;
+    Ok(())
+}
+
+pub fn buffer_remaining(env: &mut JNIEnv, byte_buffer: &JObject) -> Result<i32, NfscrsJniError> {
+    let cache = jni_cache()?;
+    let remaining = unsafe {
+        env.call_method_unchecked(
+            byte_buffer,
+            cache.buffer_remaining,
+            ReturnType::Primitive(Primitive::Int),
+            &[],
+        )
+    }?
+    .i()?;
+    Ok(remaining)
+}
+
+pub fn buffer_is_read_only(
+    env: &mut JNIEnv,
+    byte_buffer: &JObject,
+) -> Result<bool, NfscrsJniError> {
+    let cache = jni_cache()?;
+    let read_only = unsafe {
+        env.call_method_unchecked(
+            byte_buffer,
+            cache.buffer_is_read_only,
+            ReturnType::Primitive(Primitive::Boolean),
+            &[],
+        )
+    }?
+    .z()?;
+    Ok(read_only)
+}
+
+/// Copies `data` into the buffer at its current position and advances the
+/// position by `data.len()`. Read-only buffers are rejected with
+/// `ReadOnlyBufferException`.
+///
+/// Direct buffers take a single copy, from the READ reply straight into
+/// their memory; heap buffers go through a temporary `byte[]` and
+/// `ByteBuffer.put([B)`, which advances the position itself.
+pub fn put_into_buffer(
+    env: &mut JNIEnv,
+    byte_buffer: &JObject, // ByteBuffer
+    data: &[u8],
+) -> Result<(), NfscrsJniError> {
+    if data.is_empty() {
+        return Ok(());
+    }
+    if buffer_is_read_only(env, byte_buffer)? {
+        return Err(NfscrsJniError::ReadOnlyBuffer);
+    }
+    if let Ok(addr) = env.get_direct_buffer_address(byte_buffer.into()) {
+        let position = buffer_position(env, byte_buffer)?;
+        let remaining = buffer_remaining(env, byte_buffer)?;
+        if data.len() > remaining as usize {
+            return Err(NfscrsJniError::NFSCRSJNIError(format!(
+                "buffer overflow: {} bytes into {} remaining",
+                data.len(),
+                remaining
+            )));
+        }
+        let dst =
+            unsafe { std::slice::from_raw_parts_mut(addr.add(position as usize), data.len()) };
+        dst.copy_from_slice(data);
+        set_buffer_position(env, byte_buffer, position + data.len() as i32)?;
+    } else {
+        let byte_array = JObject::from(env.byte_array_from_slice(data)?);
+        let ret = env
+            .call_method(
+                byte_buffer,
+                "put",
+                "([B)Ljava/nio/ByteBuffer;",
+                &[JValue::Object(&byte_array)],
+            )?
+            .l()?;
+        env.delete_local_ref(ret)?;
+        env.delete_local_ref(byte_array)?;
+    }
+    Ok(())
+}
+
+/// Returns the `remaining()` bytes of the buffer starting at `position()`,
+/// without moving the position.
+///
+/// Direct buffers are borrowed in place. Heap buffers are copied out of the
+/// backing array (honoring `arrayOffset()`), and read-only heap buffers, whose
+/// `array()` throws, are copied through a `duplicate()`.
+pub fn buffer_bytes<'b>(
+    env: &mut JNIEnv,
+    byte_buffer: &'b JObject, // ByteBuffer
+) -> Result<Cow<'b, [u8]>, NfscrsJniError> {
+    let position = buffer_position(env, byte_buffer)?;
+    let remaining = buffer_remaining(env, byte_buffer)?;
+    if remaining <= 0 {
+        return Ok(Cow::Borrowed(&[]));
+    }
+
+    if let Ok(addr) = env.get_direct_buffer_address(byte_buffer.into()) {
+        let slice =
+            unsafe { std::slice::from_raw_parts(addr.add(position as usize), remaining as usize) };
+        return Ok(Cow::Borrowed(slice));
+    }
+
+    let has_array = env.call_method(byte_buffer, "hasArray", "()Z", &[])?.z()?;
+    if has_array {
+        let array_offset = env
+            .call_method(byte_buffer, "arrayOffset", "()I", &[])?
+            .i()?;
+        let byte_array_obj = env.call_method(byte_buffer, "array", "()[B", &[])?.l()?;
+        let byte_array = JByteArray::from(byte_array_obj);
+        let mut v = vec![0u8; remaining as usize];
+        let v_i8: &mut [i8] =
+            unsafe { std::slice::from_raw_parts_mut(v.as_mut_ptr() as *mut i8, v.len()) };
+        env.get_byte_array_region(&byte_array, array_offset + position, v_i8)?;
+        env.delete_local_ref(byte_array)?;
+        Ok(Cow::Owned(v))
+    } else {
+        let duplicate = env
+            .call_method(byte_buffer, "duplicate", "()Ljava/nio/ByteBuffer;", &[])?
+            .l()?;
+        let byte_array = env.new_byte_array(remaining)?;
+        env.call_method(
+            &duplicate,
+            "get",
+            "([B)Ljava/nio/ByteBuffer;",
+            &[JValue::Object(&byte_array)],
+        )?;
+        let v = env.convert_byte_array(&byte_array)?;
+        env.delete_local_ref(byte_array)?;
+        env.delete_local_ref(duplicate)?;
+        Ok(Cow::Owned(v))
+    }
+}
+
+/// Moves the buffer position forward by `count` bytes.
+pub fn advance_buffer(
+    env: &mut JNIEnv,
+    byte_buffer: &JObject,
+    count: i32,
+) -> Result<(), NfscrsJniError> {
+    let position = buffer_position(env, byte_buffer)?;
+    set_buffer_position(env, byte_buffer, position + count)
+}
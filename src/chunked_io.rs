@@ -0,0 +1,119 @@
+use nfscrs::nfscrs_error::NFSCRSError;
+use nfscrs::{NFSClientSession, OpenedFile};
+
+use crate::attr_utils::get_max_read_write;
+use crate::session::SessionGuard;
+use nfscrs::nfscrs_types::AbsolutePath;
+
+/// Used when the server does not report maxread/maxwrite.
+const DEFAULT_IO_SIZE: usize = 64 * 1024;
+
+/// Room left for the COMPOUND header and the other operations (SEQUENCE,
+/// PUTFH) when fitting a READ/WRITE payload into the fore channel's
+/// ca_maxrequestsize/ca_maxresponsesize.
+const COMPOUND_OVERHEAD: usize = 4096;
+
+/// READ/WRITE sizes requested for a session (`readSize`/`writeSize`).
+/// `None` uses the server's maxread/maxwrite.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IoSizeLimits {
+    pub read_size: Option<usize>,
+    pub write_size: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct IoSizes {
+    pub max_read: usize,
+    pub max_write: usize,
+}
+
+impl IoSizes {
+    /// The server's maxread/maxwrite for `path`, lowered to the session's
+    /// requested sizes and to what fits in one compound.
+    pub fn query(
+        session_ref: &mut SessionGuard,
+        path: &AbsolutePath,
+    ) -> Result<IoSizes, NFSCRSError> {
+        let limits = session_ref.io_size_limits();
+        let (max_read, max_write) = get_max_read_write(session_ref, path)?;
+        let channel = session_ref.fore_channel_attrs();
+        let payload_limit = |size: u32| (size as usize).saturating_sub(COMPOUND_OVERHEAD).max(1);
+        let clamp = |size: Option<u64>, limit: usize| {
+            size.map_or(DEFAULT_IO_SIZE, |s| s as usize).clamp(1, limit)
+        };
+        let max_read = clamp(max_read, payload_limit(channel.ca_maxresponsesize));
+        let max_write = clamp(max_write, payload_limit(channel.ca_maxrequestsize));
+        Ok(IoSizes {
+            max_read: limits.read_size.map_or(max_read, |size| size.min(max_read)),
+            max_write: limits
+                .write_size
+                .map_or(max_write, |size| size.min(max_write)),
+        })
+    }
+}
+
+pub struct ChunkedRead {
+    pub data: Vec<u8>,
+    pub eof: bool,
+}
+
+/// Reads `len` bytes at `offset` in READs of at most `max_read` bytes,
+/// stopping early only at end of file. Retries are up to the caller, which
+/// re-runs the whole read without holding the locks during the backoff.
+///
+/// The READs are sent one after another, not pipelined: `NFSClientSession`
+/// issues one compound at a time on its single slot.
+pub fn read_chunked(
+    session_ref: &mut NFSClientSession,
+    opened_file_ref: &mut OpenedFile,
+    offset: usize,
+    len: usize,
+    max_read: usize,
+) -> Result<ChunkedRead, NFSCRSError> {
+    let mut data: Vec<u8> = Vec::new();
+    let mut eof = false;
+    while data.len() < len {
+        let want = (len - data.len()).min(max_read);
+        let read_result = session_ref.read(opened_file_ref, offset + data.len(), want)?;
+        let got = read_result.data.len();
+        if data.is_empty() {
+            // The first reply is kept as it is, so a read that fits in one
+            // READ is not copied here.
+            data = read_result.data;
+        } else {
+            data.extend_from_slice(&read_result.data);
+        }
+        if read_result.eof || got == 0 {
+            eof = true;
+            break;
+        }
+    }
+    Ok(ChunkedRead { data, eof })
+}
+
+/// Writes all of `data` at `offset` in WRITEs of at most `max_write` bytes,
+/// re-issuing the remainder after short counts. Like `read_chunked`, the
+/// WRITEs are sequential and not retried here; re-running the whole write
+/// only rewrites the same bytes at the same offsets.
+pub fn write_chunked(
+    session_ref: &mut NFSClientSession,
+    opened_file_ref: &mut OpenedFile,
+    offset: usize,
+    data: &[u8],
+    max_write: usize,
+) -> Result<usize, NFSCRSError> {
+    let mut written = 0;
+    while written < data.len() {
+        let end = data.len().min(written + max_write);
+        let write_result =
+            session_ref.write(opened_file_ref, offset + written, &data[written..end])?;
+        if write_result.count == 0 {
+            return Err(NFSCRSError::OperationError(format!(
+                "server accepted 0 bytes at offset {}",
+                offset + written
+            )));
+        }
+        written += write_result.count as usize;
+    }
+    Ok(written)
+}
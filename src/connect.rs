@@ -0,0 +1,136 @@
+use std::net::{Ipv4Addr, SocketAddr, TcpStream, ToSocketAddrs};
+use std::os::fd::{FromRawFd, OwnedFd, RawFd};
+
+use nfscrs::NFSClientSession;
+use nfscrs::nfscrs_error::NFSCRSError;
+
+use crate::error::NfscrsJniError;
+use crate::session_options::SessionOptions;
+
+/// Establishes a session with the first address of `options.server_address`
+/// that accepts a connection.
+///
+/// Addresses are tried one at a time, alternating between IPv6 and IPv4 as
+/// in Happy Eyeballs (RFC 8305), each bounded by the connect timeout. Only
+/// connection failures move on to the next address; once a server answers,
+/// its errors are returned as they are. When every address fails, the
+/// error lists each address with its failure.
+pub fn establish(options: &SessionOptions) -> Result<NFSClientSession, NfscrsJniError> {
+    let addrs: Vec<SocketAddr> = options
+        .server_address
+        .to_socket_addrs()
+        .map_err(|e| NfscrsJniError::UnknownHost(format!("{}: {e}", options.server_address)))?
+        .collect();
+    if addrs.is_empty() {
+        return Err(NfscrsJniError::UnknownHost(format!(
+            "{}: could not resolve to any address",
+            options.server_address
+        )));
+    }
+
+    let mut failures = Vec::new();
+    for addr in interleave_families(addrs) {
+        tracing::debug!("connecting to {addr}");
+        match options.builder(addr).establish_session() {
+            Ok(session) => return Ok(session),
+            Err(NFSCRSError::Connection(e)) => {
+                tracing::debug!("connect to {addr} failed: {e}");
+                failures.push(format!("{addr}: {e}"));
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(NfscrsJniError::ConnectError(format!(
+        "failed to connect to {}: {}",
+        options.server_address,
+        failures.join("; ")
+    )))
+}
+
+/// Orders addresses IPv6, IPv4, IPv6, ... keeping the resolver's order
+/// within each family, starting with the family of the first address.
+fn interleave_families(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let first_is_v6 = addrs[0].is_ipv6();
+    let (mut first, mut second): (Vec<_>, Vec<_>) =
+        addrs.into_iter().partition(|a| a.is_ipv6() == first_is_v6);
+    let mut ordered = Vec::with_capacity(first.len() + second.len());
+    first.reverse();
+    second.reverse();
+    loop {
+        match (first.pop(), second.pop()) {
+            (None, None) => break,
+            (a, b) => ordered.extend(a.into_iter().chain(b)),
+        }
+    }
+    ordered
+}
+
+/// Takes ownership of a socket fd handed over from Java. From here on the
+/// fd is closed when the returned stream is dropped, whatever happens.
+///
+/// # Safety
+/// `fd` must be an open descriptor that nothing else will close.
+pub unsafe fn claim_socket(fd: RawFd) -> Result<TcpStream, NfscrsJniError> {
+    if fd < 0 {
+        return Err(NfscrsJniError::IllegalArgument(format!(
+            "invalid file descriptor: {fd}"
+        )));
+    }
+    Ok(TcpStream::from(unsafe { OwnedFd::from_raw_fd(fd) }))
+}
+
+/// Establishes a session over an already connected stream socket.
+/// `options.server_address` is ignored.
+///
+/// The socket need not have a peer address, e.g. one end of a socketpair
+/// to a proxy; the builder is then given the unspecified address, which it
+/// does not connect to when handed a stream.
+pub fn establish_over_socket(
+    options: &SessionOptions,
+    stream: TcpStream,
+) -> Result<NFSClientSession, NfscrsJniError> {
+    let peer = match stream.peer_addr() {
+        Ok(peer) => {
+            tracing::debug!("establishing session over socket to {peer}");
+            peer
+        }
+        Err(e) => {
+            tracing::debug!("establishing session over socket without peer address: {e}");
+            SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0))
+        }
+    };
+    Ok(options.builder(peer).establish_session_over(stream)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addrs(addrs: &[&str]) -> Vec<SocketAddr> {
+        addrs.iter().map(|a| a.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn interleaves_mixed_families_starting_with_the_first() {
+        let input = addrs(&["[::1]:2049", "[::2]:2049", "[::3]:2049", "10.0.0.1:2049"]);
+        assert_eq!(
+            interleave_families(input),
+            addrs(&["[::1]:2049", "10.0.0.1:2049", "[::2]:2049", "[::3]:2049"])
+        );
+
+        let input = addrs(&["10.0.0.1:2049", "10.0.0.2:2049", "[::1]:2049", "[::2]:2049"]);
+        assert_eq!(
+            interleave_families(input),
+            addrs(&["10.0.0.1:2049", "[::1]:2049", "10.0.0.2:2049", "[::2]:2049"])
+        );
+    }
+
+    #[test]
+    fn keeps_a_single_family_in_order() {
+        let v4 = addrs(&["10.0.0.1:2049", "10.0.0.2:2049", "10.0.0.3:2049"]);
+        assert_eq!(interleave_families(v4.clone()), v4);
+
+        let v6 = addrs(&["[::1]:2049", "[::2]:2049"]);
+        assert_eq!(interleave_families(v6.clone()), v6);
+    }
+}
@@ -0,0 +1,323 @@
+use std::time::Duration;
+
+use jni::JNIEnv;
+use jni::objects::{JObject, JString};
+use jni::sys::{jint, jlong};
+use nfscrs::nfs4_types::NFSStat4;
+use nfscrs::nfscrs_error::NFSCRSError;
+use nfscrs::nfscrs_types::AbsolutePath;
+use nfscrs::{OpenOptions, OpenedFile};
+
+use crate::attr_utils::{get_file_size, get_fs_position};
+use crate::basic_attr_bitmap;
+use crate::chunked_io::{IoSizes, read_chunked, write_chunked};
+use crate::error::{NfscrsJniError, handle_error, is_not_supported};
+use crate::file_utils::{CopyOptions, int_to_copy_options};
+use crate::retry::retry;
+use crate::session::{SessionGuard, SessionHandle, session_handle};
+use crate::sparse_ops::next_data_segment;
+use crate::transfer::report_progress;
+
+/// Delay between OFFLOAD_STATUS polls of an asynchronous COPY.
+const OFFLOAD_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Copies `src` to `dst` on the same server.
+///
+/// CLONE is tried first, then COPY (polled with OFFLOAD_STATUS when the
+/// server runs it asynchronously), then a copy through the client. `options`
+/// is a `COPY_*_BIT_NUM` bitmask. `progress` may be null; when its
+/// `onProgress` throws (e.g. a `CancellationException`), the copy is
+/// abandoned, an asynchronous COPY is cancelled with OFFLOAD_CANCEL, and the
+/// exception propagates. Returns the number of bytes copied. As for
+/// `downloadToFd`, the session's timeout bounds each RPC and each step is
+/// retried on its own.
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_copyFile(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    src: JString,
+    dst: JString,
+    options: jint,
+    progress: JObject,
+) -> jlong {
+    let session_handle = unsafe { session_handle(session) };
+    let opts = int_to_copy_options(options);
+    match copy_file(session_handle, &mut env, &src, &dst, opts, &progress) {
+        Ok(r) => r as jlong,
+        Err(e) => {
+            handle_error(&mut env, &e);
+            return -1;
+        }
+    }
+}
+
+fn copy_file(
+    session_handle: &SessionHandle,
+    env: &mut JNIEnv,
+    src: &JString,
+    dst: &JString,
+    opts: CopyOptions,
+    progress: &JObject, // NFS4ProgressCallback
+) -> Result<u64, NfscrsJniError> {
+    let src_str: String = env.get_string(src)?.into();
+    let dst_str: String = env.get_string(dst)?.into();
+
+    let policy = session_handle.policy();
+    let (mut src_file, mut dst_file, size, io_sizes) = retry(&policy, "copyFile", || {
+        let mut session_ref = session_handle.lock();
+        let src_path = session_ref.resolve(src_str.clone())?;
+        let dst_path = session_ref.resolve(dst_str.clone())?;
+        tracing::debug!("copy_file: {:?} -> {:?}", src_path, dst_path);
+        // Truncating the destination would destroy the source.
+        if opts.replace_existing && is_same_file(&mut session_ref, &src_path, &dst_path)? {
+            return Err(NfscrsJniError::IllegalArgument(format!(
+                "source and destination are the same file: {dst_str}"
+            )));
+        }
+        let fattr4 = session_ref.get_attr(&src_path, basic_attr_bitmap())?;
+        let size = get_file_size(&fattr4)?;
+        let io_sizes = IoSizes::query(&mut session_ref, &src_path)?;
+        let mut src_file = session_ref.open_file(&src_path, OpenOptions::new().read(true))?;
+        // Without replace_existing the destination is created exclusively,
+        // so a file appearing concurrently is not overwritten.
+        let dst_options = if opts.replace_existing {
+            OpenOptions::new().write(true).create(true).truncate(true)
+        } else {
+            OpenOptions::new().write(true).create_new(true)
+        };
+        let dst_file = match session_ref.open_file(&dst_path, dst_options) {
+            Ok(f) => f,
+            Err(e) => {
+                let _ = session_ref.close(&mut src_file);
+                return Err(match e {
+                    NFSCRSError::NFSStatError(NFSStat4::NFS4ERR_EXIST) => {
+                        NfscrsJniError::FileAlreadyExists(dst_str.clone())
+                    }
+                    e => e.into(),
+                });
+            }
+        };
+        Ok((src_file, dst_file, size, io_sizes))
+    })?;
+
+    let result = copy_opened(
+        session_handle,
+        &mut src_file,
+        &mut dst_file,
+        size,
+        io_sizes,
+        opts,
+        progress,
+        env,
+    );
+
+    let close_src = retry(&policy, "copyFile", || {
+        session_handle.lock().close(&mut src_file)
+    });
+    let close_dst = retry(&policy, "copyFile", || {
+        session_handle.lock().close(&mut dst_file)
+    });
+    let copied = result?;
+    close_src?;
+    close_dst?;
+    tracing::debug!("copy_file ok : {copied} bytes");
+    Ok(copied)
+}
+
+/// Whether `dst` exists and is the same file as `src` (same fsid and
+/// fileid), which also catches hard links and differently spelled paths.
+fn is_same_file(
+    session_ref: &mut SessionGuard,
+    src: &AbsolutePath,
+    dst: &AbsolutePath,
+) -> Result<bool, NfscrsJniError> {
+    let src_position = get_fs_position(session_ref, src)?;
+    let dst_position = match get_fs_position(session_ref, dst) {
+        Ok(position) => position,
+        Err(NfscrsJniError::NFSCRSError(NFSCRSError::NFSStatError(NFSStat4::NFS4ERR_NOENT))) => {
+            return Ok(false);
+        }
+        Err(e) => return Err(e),
+    };
+    Ok(src_position.fsid == dst_position.fsid
+        && src_position.fileid.is_some()
+        && src_position.fileid == dst_position.fileid)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn copy_opened(
+    session_handle: &SessionHandle,
+    src_file: &mut OpenedFile,
+    dst_file: &mut OpenedFile,
+    size: u64,
+    io_sizes: IoSizes,
+    opts: CopyOptions,
+    progress: &JObject,
+    env: &mut JNIEnv,
+) -> Result<u64, NfscrsJniError> {
+    let policy = session_handle.policy();
+    if !opts.client_side_only && size > 0 {
+        let clone_result = retry(&policy, "copyFile", || {
+            session_handle
+                .lock()
+                .clone_range(src_file, dst_file, 0, 0, size)
+        });
+        match clone_result {
+            Ok(()) => {
+                tracing::debug!("copy_file: cloned");
+                report_progress(env, progress, size, size as i64)?;
+                return Ok(size);
+            }
+            Err(e) if is_not_supported(&e) => {
+                tracing::debug!("copy_file: CLONE not supported: {e}");
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        match server_side_copy(session_handle, src_file, dst_file, size, progress, env) {
+            Ok(copied) => {
+                tracing::debug!("copy_file: copied on server");
+                return Ok(copied);
+            }
+            Err(NfscrsJniError::NFSCRSError(e)) if is_not_supported(&e) => {
+                tracing::debug!("copy_file: COPY not supported: {e}");
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    client_side_copy(
+        session_handle,
+        src_file,
+        dst_file,
+        size,
+        io_sizes,
+        progress,
+        env,
+    )
+}
+
+/// Runs COPY until `size` bytes are copied, following asynchronous copies
+/// with OFFLOAD_STATUS.
+fn server_side_copy(
+    session_handle: &SessionHandle,
+    src_file: &mut OpenedFile,
+    dst_file: &mut OpenedFile,
+    size: u64,
+    progress: &JObject,
+    env: &mut JNIEnv,
+) -> Result<u64, NfscrsJniError> {
+    let policy = session_handle.policy();
+    let mut copied: u64 = 0;
+    while copied < size {
+        let copy_result = retry(&policy, "copyFile", || {
+            session_handle
+                .lock()
+                .copy_range(src_file, dst_file, copied, copied, size - copied)
+        })?;
+        let count = match copy_result.callback_id {
+            None => copy_result.count,
+            Some(stateid) => loop {
+                std::thread::sleep(OFFLOAD_POLL_INTERVAL);
+                let status = retry(&policy, "copyFile", || {
+                    session_handle.lock().offload_status(dst_file, &stateid)
+                })?;
+                if let Err(e) = report_progress(env, progress, copied + status.count, size as i64) {
+                    let _ = session_handle.lock().offload_cancel(dst_file, &stateid);
+                    return Err(e);
+                }
+                match status.complete {
+                    None => continue,
+                    Some(NFSStat4::NFS4_OK) => break status.count,
+                    Some(stat) => return Err(NFSCRSError::NFSStatError(stat).into()),
+                }
+            },
+        };
+        if count == 0 {
+            return Err(NfscrsJniError::NFSCRSJNIError(format!(
+                "server COPY made no progress at offset {copied}"
+            )));
+        }
+        copied += count;
+        report_progress(env, progress, copied, size as i64)?;
+    }
+    Ok(copied)
+}
+
+/// Copies the data segments of `src_file` through the client, skipping holes
+/// (the destination was truncated, so they stay holes there).
+fn client_side_copy(
+    session_handle: &SessionHandle,
+    src_file: &mut OpenedFile,
+    dst_file: &mut OpenedFile,
+    size: u64,
+    io_sizes: IoSizes,
+    progress: &JObject,
+    env: &mut JNIEnv,
+) -> Result<u64, NfscrsJniError> {
+    let policy = session_handle.policy();
+    let chunk_size = io_sizes.max_read.min(io_sizes.max_write) as u64;
+    let mut pos: u64 = 0;
+    let mut segment_end: u64 = 0;
+    while pos < size {
+        let step = retry(&policy, "copyFile", || -> Result<_, NFSCRSError> {
+            let mut session_ref = session_handle.lock();
+            if pos >= segment_end {
+                match next_data_segment(&mut session_ref, src_file, pos, size)? {
+                    Some((start, stop)) => {
+                        pos = start;
+                        segment_end = stop;
+                    }
+                    None => {
+                        // Only holes remain; extend the truncated destination
+                        // to the full size with its last byte.
+                        write_chunked(&mut session_ref, dst_file, (size - 1) as usize, &[0], 1)?;
+                        pos = size;
+                        return Ok(None);
+                    }
+                }
+            }
+            let want = (segment_end - pos).min(chunk_size) as usize;
+            let n = copy_chunk(&mut session_ref, src_file, dst_file, pos, want, io_sizes)?;
+            Ok(Some((want, n)))
+        })?;
+        let Some((want, n)) = step else {
+            break;
+        };
+        pos += n as u64;
+        report_progress(env, progress, pos, size as i64)?;
+        if n < want {
+            // The source shrank while copying.
+            break;
+        }
+    }
+    report_progress(env, progress, pos, size as i64)?;
+    Ok(pos)
+}
+
+fn copy_chunk(
+    session_ref: &mut SessionGuard,
+    src_file: &mut OpenedFile,
+    dst_file: &mut OpenedFile,
+    offset: u64,
+    len: usize,
+    io_sizes: IoSizes,
+) -> Result<usize, NFSCRSError> {
+    let read_result = read_chunked(
+        session_ref,
+        src_file,
+        offset as usize,
+        len,
+        io_sizes.max_read,
+    )?;
+    write_chunked(
+        session_ref,
+        dst_file,
+        offset as usize,
+        &read_result.data,
+        io_sizes.max_write,
+    )?;
+    Ok(read_result.data.len())
+}
@@ -0,0 +1,93 @@
+use jni::JNIEnv;
+use jni::objects::{JIntArray, JString};
+use jni::sys::jint;
+use nfscrs::{NFSClientBuilder, NFSClientSession};
+
+use crate::error::NfscrsJniError;
+
+/// AUTH_SYS allows at most 16 supplementary gids (RFC 5531, appendix A).
+const AUTH_SYS_MAX_GIDS: usize = 16;
+/// AUTH_SYS limits the machine name to 255 bytes.
+const AUTH_SYS_MAX_MACHINE_NAME: usize = 255;
+
+/// AUTH_SYS credential sent with every RPC of a session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthSysCredential {
+    pub uid: u32,
+    pub gid: u32,
+    pub gids: Vec<u32>,
+    /// `None` keeps the nfscrs default.
+    pub machine_name: Option<String>,
+}
+
+impl AuthSysCredential {
+    pub fn new(uid: u32, gid: u32) -> AuthSysCredential {
+        AuthSysCredential {
+            uid,
+            gid,
+            gids: Vec::new(),
+            machine_name: None,
+        }
+    }
+
+    /// Builds a credential from Java arguments. `gids` and `machine_name`
+    /// may be null.
+    pub fn from_java(
+        env: &mut JNIEnv,
+        uid: jint,
+        gid: jint,
+        gids: &JIntArray,
+        machine_name: &JString,
+    ) -> Result<AuthSysCredential, NfscrsJniError> {
+        let mut credential = AuthSysCredential::new(uid as u32, gid as u32);
+        if !gids.is_null() {
+            let len = env.get_array_length(gids)? as usize;
+            let mut buf = vec![0; len];
+            env.get_int_array_region(gids, 0, &mut buf)?;
+            credential.gids = buf.into_iter().map(|g| g as u32).collect();
+        }
+        if !machine_name.is_null() {
+            credential.machine_name = Some(env.get_string(machine_name)?.into());
+        }
+        credential.validate()?;
+        Ok(credential)
+    }
+
+    pub fn validate(&self) -> Result<(), NfscrsJniError> {
+        if self.gids.len() > AUTH_SYS_MAX_GIDS {
+            return Err(NfscrsJniError::IllegalArgument(format!(
+                "AUTH_SYS allows at most {AUTH_SYS_MAX_GIDS} supplementary gids, got {}",
+                self.gids.len()
+            )));
+        }
+        if let Some(name) = &self.machine_name
+            && name.len() > AUTH_SYS_MAX_MACHINE_NAME
+        {
+            return Err(NfscrsJniError::IllegalArgument(format!(
+                "AUTH_SYS machine name is longer than {AUTH_SYS_MAX_MACHINE_NAME} bytes"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Applies the supplementary gids and machine name to a builder created
+    /// with this credential's uid and gid.
+    pub fn configure(&self, builder: NFSClientBuilder) -> NFSClientBuilder {
+        let builder = builder.gids(self.gids.clone());
+        match &self.machine_name {
+            Some(name) => builder.machine_name(name.clone()),
+            None => builder,
+        }
+    }
+
+    /// Installs this credential in a live session. Later RPCs are sent
+    /// with it; opened files and the lease are unaffected.
+    pub fn apply(&self, session_ref: &mut NFSClientSession) {
+        session_ref.set_auth_sys(
+            self.uid,
+            self.gid,
+            self.gids.clone(),
+            self.machine_name.clone(),
+        );
+    }
+}
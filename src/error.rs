@@ -1,8 +1,12 @@
 use jni::JNIEnv;
+use jni::objects::{GlobalRef, JMethodID, JThrowable, JValue};
 
+use nfscrs::nfs4_types::NFSStat4;
 use nfscrs::nfscrs_error::NFSCRSError;
 use thiserror::Error;
 
+use crate::jni_utils::{JniCache, as_class, jni_cache};
+
 #[derive(Debug, Error)]
 pub enum NfscrsJniError {
     #[error("JNIError: {0:?}")]
@@ -11,6 +15,29 @@ pub enum NfscrsJniError {
     NFSCRSError(#[from] NFSCRSError),
     #[error("NFSCRSJNIError: {0}")]
     NFSCRSJNIError(String),
+    #[error("IllegalArgument: {0}")]
+    IllegalArgument(String),
+    #[error("UnsupportedOperation: {0}")]
+    UnsupportedOperation(String),
+    #[error("IOError: {0}")]
+    IOError(#[from] std::io::Error),
+    #[error("FileAlreadyExists: {0}")]
+    FileAlreadyExists(String),
+    #[error("FileSystemError: {0}")]
+    FileSystemError(String),
+    #[error("UnknownHost: {0}")]
+    UnknownHost(String),
+    #[error("ConnectError: {0}")]
+    ConnectError(String),
+    /// Input and reason of a `URISyntaxException`.
+    #[error("URISyntax: {0}: {1}")]
+    URISyntax(String, String),
+    #[error("ReadOnlyBuffer")]
+    ReadOnlyBuffer,
+    #[error("Interrupted: {0}")]
+    Interrupted(String),
+    #[error("Timeout: {0}")]
+    Timeout(String),
 }
 
 pub fn throw_nfs_error(env: &mut JNIEnv, err: &NFSCRSError) {
@@ -35,6 +62,10 @@ pub fn throw_nfs_error(env: &mut JNIEnv, err: &NFSCRSError) {
             ("java/io/IOException", format!("ONC RPC reply denied: {s}"))
         }
         NFSCRSError::EmptyReplyBody => ("java/io/EOFException", "Empty reply body".to_string()),
+        NFSCRSError::NFSStatError(NFSStat4::NFS4ERR_WRONGSEC) => (
+            "java/lang/SecurityException",
+            "Server requires a security flavor other than AUTH_SYS".to_string(),
+        ),
         NFSCRSError::NFSStatError(stat) => {
             ("java/io/IOException", format!("NFSStat error: {stat:?}"))
         }
@@ -48,8 +79,24 @@ pub fn throw_nfs_error(env: &mut JNIEnv, err: &NFSCRSError) {
     let _ = env.throw_new(class, msg);
 }
 
+/// Whether the server rejected an operation it does not implement, so the
+/// caller can fall back to another strategy.
+pub fn is_not_supported(err: &NFSCRSError) -> bool {
+    matches!(
+        err,
+        NFSCRSError::NFSStatError(
+            NFSStat4::NFS4ERR_NOTSUPP
+                | NFSStat4::NFS4ERR_OP_ILLEGAL
+                | NFSStat4::NFS4ERR_XDEV
+                | NFSStat4::NFS4ERR_MINOR_VERS_MISMATCH
+        )
+    )
+}
+
 pub fn handle_error(env: &mut JNIEnv, e: &NfscrsJniError) {
     match e {
+        // A Java exception (e.g. thrown by a callback) is already pending.
+        NfscrsJniError::JNIError(jni::errors::Error::JavaException) => {}
         NfscrsJniError::JNIError(e) => {
             let _ = env.throw_new("java/lang/RuntimeException", e.to_string());
         }
@@ -59,5 +106,81 @@ pub fn handle_error(env: &mut JNIEnv, e: &NfscrsJniError) {
         NfscrsJniError::NFSCRSJNIError(e) => {
             let _ = env.throw_new("java/lang/RuntimeException", e.to_string());
         }
+        NfscrsJniError::IllegalArgument(e) => {
+            let _ = env.throw_new("java/lang/IllegalArgumentException", e.to_string());
+        }
+        NfscrsJniError::UnsupportedOperation(e) => {
+            let _ = env.throw_new("java/lang/UnsupportedOperationException", e.to_string());
+        }
+        NfscrsJniError::IOError(e) => {
+            let _ = env.throw_new("java/io/IOException", e.to_string());
+        }
+        NfscrsJniError::FileAlreadyExists(path) => {
+            let _ = env.throw_new("java/nio/file/FileAlreadyExistsException", path.to_string());
+        }
+        NfscrsJniError::FileSystemError(e) => {
+            let _ = env.throw_new("java/nio/file/FileSystemException", e.to_string());
+        }
+        NfscrsJniError::UnknownHost(e) => {
+            let _ = env.throw_new("java/net/UnknownHostException", e.to_string());
+        }
+        NfscrsJniError::ConnectError(e) => {
+            let _ = env.throw_new("java/net/ConnectException", e.to_string());
+        }
+        NfscrsJniError::URISyntax(input, reason) => {
+            // URISyntaxException has no single-message constructor.
+            if let Ok(cache) = jni_cache() {
+                throw_uri_syntax(env, cache, input, reason);
+            }
+        }
+        NfscrsJniError::ReadOnlyBuffer => {
+            if let Ok(cache) = jni_cache() {
+                throw_without_message(
+                    env,
+                    &cache.read_only_buffer_exception_class,
+                    cache.read_only_buffer_exception_ctor,
+                );
+            }
+        }
+        NfscrsJniError::Timeout(e) => {
+            let _ = env.throw_new("java/net/SocketTimeoutException", e.to_string());
+        }
+        NfscrsJniError::Interrupted(e) => {
+            // ClosedByInterruptException has no message constructor.
+            tracing::debug!("interrupted: {e}");
+            if let Ok(cache) = jni_cache() {
+                throw_without_message(
+                    env,
+                    &cache.closed_by_interrupt_exception_class,
+                    cache.closed_by_interrupt_exception_ctor,
+                );
+            }
+        }
+    }
+}
+
+/// Throws a cached exception class that only has a no-argument constructor,
+/// which `throw_new` cannot build.
+fn throw_without_message(env: &mut JNIEnv, class: &GlobalRef, ctor: JMethodID) {
+    if let Ok(exception) = unsafe { env.new_object_unchecked(as_class(class), ctor, &[]) } {
+        let _ = env.throw(JThrowable::from(exception));
+    }
+}
+
+fn throw_uri_syntax(env: &mut JNIEnv, cache: &JniCache, input: &str, reason: &str) {
+    let (Ok(input), Ok(reason)) = (env.new_string(input), env.new_string(reason)) else {
+        return;
+    };
+    if let Ok(exception) = unsafe {
+        env.new_object_unchecked(
+            as_class(&cache.uri_syntax_exception_class),
+            cache.uri_syntax_exception_ctor,
+            &[
+                JValue::Object(&input).as_jni(),
+                JValue::Object(&reason).as_jni(),
+            ],
+        )
+    } {
+        let _ = env.throw(JThrowable::from(exception));
     }
 }
@@ -0,0 +1,262 @@
+use std::collections::VecDeque;
+
+use jni::JNIEnv;
+use jni::objects::{JObject, JValue};
+use jni::sys::{jlong, jobject};
+use nfscrs::nfs4_types::NFSStat4;
+use nfscrs::nfscrs_error::NFSCRSError;
+use nfscrs::nfscrs_types::AbsolutePath;
+
+use crate::attr_utils::{FsPosition, fs_position_bitmap, fs_position_from_fattr, get_fs_position};
+use crate::error::{NfscrsJniError, handle_error};
+use crate::interrupt::interruptible;
+use crate::jni_utils::{JniCache, as_class, jni_cache};
+use crate::retry::{OperationPolicy, retry_idempotent};
+use crate::security::{SecFlavor, security_flavors};
+use crate::session::{SessionHandle, session_handle};
+
+/// Pseudo-filesystem directories visited before giving up, in case the
+/// server exports its root directly and the walk lands in real data.
+const MAX_PSEUDO_DIRS: usize = 256;
+
+#[derive(Debug)]
+struct ExportInfo {
+    path: String,
+    fsid: (u64, u64),
+    /// `None` when the server does not implement SECINFO or it failed.
+    flavors: Option<Vec<SecFlavor>>,
+}
+
+/// Lists the exports of the server as `List<NFS4ExportInfo>`, each with
+/// its path, fsid and `sec=` flavors (null when unknown).
+///
+/// The pseudo-filesystem is walked from the server root, independent of
+/// the session root. A directory is an export when its fsid differs from
+/// its parent's, or when its mounted_on_fileid differs from its fileid;
+/// exports are not descended into. A pseudo-filesystem holds nothing but
+/// directories, so the walk stops at the first directory holding anything
+/// else: the server exports its root directly. When nothing below the root
+/// is an export, the root itself is reported.
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_listExports(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+) -> jobject {
+    let session_handle = unsafe { session_handle(session) };
+    let policy = session_handle.policy();
+    let exports = match interruptible(&mut env, "listExports", policy.timeout, move || {
+        find_exports(session_handle, &policy)
+    }) {
+        Ok(r) => r,
+        Err(e) => {
+            handle_error(&mut env, &e);
+            return std::ptr::null_mut();
+        }
+    };
+    match exports_to_java(&mut env, exports) {
+        Ok(r) => r,
+        Err(e) => {
+            handle_error(&mut env, &e);
+            return std::ptr::null_mut();
+        }
+    }
+}
+
+/// Walks the pseudo-filesystem with one READDIR per directory, which
+/// returns the fsid and type of every entry, and takes the session lock
+/// per RPC so that other calls on the session interleave with the walk.
+fn find_exports(
+    session_handle: &SessionHandle,
+    policy: &OperationPolicy,
+) -> Result<Vec<ExportInfo>, NfscrsJniError> {
+    let root = server_path("/")?;
+    let root_position = retry_idempotent(policy, "listExports", || {
+        get_fs_position(&mut session_handle.lock(), &root)
+    })?;
+    tracing::debug!("list_exports: root {:?}", root_position);
+
+    let mut exports = Vec::new();
+    let mut queue: VecDeque<(String, FsPosition)> = VecDeque::new();
+    queue.push_back(("/".to_string(), root_position));
+    let mut visited = 0;
+    while let Some((dir, dir_position)) = queue.pop_front() {
+        visited += 1;
+        if visited > MAX_PSEUDO_DIRS {
+            tracing::debug!("list_exports: stopping after {MAX_PSEUDO_DIRS} directories");
+            break;
+        }
+        let dir_path = server_path(&dir)?;
+        let entries = match retry_idempotent(policy, "listExports", || {
+            session_handle
+                .lock()
+                .list_dir_with_attrs(&dir_path, fs_position_bitmap())
+        }) {
+            Ok(entries) => entries,
+            // Exports we may not look into still show up in the listing.
+            Err(e) if dir != "/" && is_access_error(&e) => {
+                tracing::debug!("list_exports: cannot list {dir}: {e}");
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let mut subdirs = Vec::new();
+        let mut pseudo = true;
+        for entry in entries {
+            let name = String::from_utf8_lossy(&entry.name).into_owned();
+            // Entries whose attributes we may not read (rdattr_error) come
+            // without an fsid; exports we may not look into still show up.
+            let Some(position) = fs_position_from_fattr(&entry.attrs) else {
+                tracing::debug!("list_exports: no attributes for {name} in {dir}");
+                continue;
+            };
+            if !position.is_dir {
+                pseudo = false;
+                break;
+            }
+            let child = if dir == "/" {
+                format!("/{name}")
+            } else {
+                format!("{dir}/{name}")
+            };
+            subdirs.push((child, position));
+        }
+        if !pseudo {
+            tracing::debug!("list_exports: {dir} holds files, not a pseudo-filesystem");
+            break;
+        }
+        for (child, position) in subdirs {
+            if is_export_boundary(&dir_position, &position) {
+                let flavors = export_flavors(session_handle, policy, &server_path(&child)?);
+                tracing::debug!("list_exports: {child} {:?} {:?}", position.fsid, flavors);
+                exports.push(ExportInfo {
+                    path: child,
+                    fsid: position.fsid,
+                    flavors,
+                });
+            } else {
+                queue.push_back((child, position));
+            }
+        }
+    }
+
+    if exports.is_empty() {
+        exports.push(ExportInfo {
+            path: "/".to_string(),
+            fsid: root_position.fsid,
+            flavors: export_flavors(session_handle, policy, &root),
+        });
+    }
+    Ok(exports)
+}
+
+/// Flavors of one export. A failing SECINFO leaves them unknown rather
+/// than hiding the other exports.
+fn export_flavors(
+    session_handle: &SessionHandle,
+    policy: &OperationPolicy,
+    path: &AbsolutePath,
+) -> Option<Vec<SecFlavor>> {
+    let flavors = retry_idempotent(policy, "listExports", || {
+        security_flavors(&mut session_handle.lock(), path)
+    });
+    match flavors {
+        Ok(flavors) => flavors,
+        Err(e) => {
+            tracing::debug!("list_exports: SECINFO of {:?} failed: {e}", path);
+            None
+        }
+    }
+}
+
+fn is_export_boundary(parent: &FsPosition, child: &FsPosition) -> bool {
+    if child.fsid != parent.fsid {
+        return true;
+    }
+    matches!(
+        (child.mounted_on_fileid, child.fileid),
+        (Some(mounted_on), Some(fileid)) if mounted_on != fileid
+    )
+}
+
+fn is_access_error(err: &NFSCRSError) -> bool {
+    matches!(
+        err,
+        NFSCRSError::NFSStatError(
+            NFSStat4::NFS4ERR_ACCESS | NFSStat4::NFS4ERR_PERM | NFSStat4::NFS4ERR_WRONGSEC
+        )
+    )
+}
+
+fn server_path(path: &str) -> Result<AbsolutePath, NFSCRSError> {
+    AbsolutePath::try_from(path).map_err(|e| NFSCRSError::InnerError(e))
+}
+
+fn exports_to_java(env: &mut JNIEnv, exports: Vec<ExportInfo>) -> Result<jobject, NfscrsJniError> {
+    let cache = jni_cache()?;
+    let (export_info_class, export_info_ctor) = cache.export_info.get(env)?;
+    let list = new_array_list(env, cache)?;
+    for export in exports {
+        let flavors = match &export.flavors {
+            Some(flavors) => {
+                let flavor_list = new_array_list(env, cache)?;
+                for flavor in flavors {
+                    let jname = env.new_string(flavor.name())?;
+                    array_list_add(env, cache, &flavor_list, &jname)?;
+                    env.delete_local_ref(jname)?;
+                }
+                flavor_list
+            }
+            None => JObject::null(),
+        };
+        let jpath = env.new_string(&export.path)?;
+        let info = unsafe {
+            env.new_object_unchecked(
+                export_info_class,
+                export_info_ctor,
+                &[
+                    JValue::Object(&jpath).as_jni(),
+                    JValue::Long(export.fsid.0 as i64).as_jni(),
+                    JValue::Long(export.fsid.1 as i64).as_jni(),
+                    JValue::Object(&flavors).as_jni(),
+                ],
+            )
+        }?;
+        array_list_add(env, cache, &list, &info)?;
+        env.delete_local_ref(info)?;
+        env.delete_local_ref(jpath)?;
+        env.delete_local_ref(flavors)?;
+    }
+    Ok(list.into_raw())
+}
+
+fn new_array_list<'local>(
+    env: &mut JNIEnv<'local>,
+    cache: &JniCache,
+) -> Result<JObject<'local>, NfscrsJniError> {
+    Ok(unsafe {
+        env.new_object_unchecked(
+            as_class(&cache.array_list_class),
+            cache.array_list_ctor,
+            &[],
+        )
+    }?)
+}
+
+fn array_list_add(
+    env: &mut JNIEnv,
+    cache: &JniCache,
+    list: &JObject,
+    element: &JObject,
+) -> Result<(), NfscrsJniError> {
+    unsafe {
+        env.call_method_unchecked(
+            list,
+            cache.array_list_add,
+            jni::signature::ReturnType::Primitive(jni::signature::Primitive::Boolean),
+            &[JValue::Object(element).as_jni()],
+        )
+    }?;
+    Ok(())
+}
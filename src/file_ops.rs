@@ -1,21 +1,30 @@
+use std::sync::Arc;
+use std::time::Duration;
+
 use jni::sys::jboolean;
-use nfscrs::nfscrs_error::NFSCRSError;
-use nfscrs::nfscrs_types::AbsolutePath;
-use nfscrs::{NFSClientSession, OpenOptions, OpenedFile};
+use nfscrs::OpenOptions;
 
 use jni::JNIEnv;
-use jni::objects::{JByteArray, JString, JValue};
+use jni::objects::{JObjectArray, JString, JValue};
 use jni::{
     objects::JObject,
     sys::{jint, jlong, jobject},
 };
 
 use crate::basic_attr_bitmap;
+use crate::byte_buffer::{
+    advance_buffer, buffer_bytes, buffer_is_read_only, buffer_remaining, put_into_buffer,
+};
+use crate::chunked_io::{read_chunked, write_chunked};
 use crate::error::{NfscrsJniError, handle_error};
 use crate::file_utils::int_to_open_options;
-
-const NFS4_FILE_READ_RESULT_CLASS_NAME: &str = "com/algebnaly/nfs4c/NFS4FileReadResult";
-const NFS4_FILE_WRITE_RESULT_CLASS_NAME: &str = "com/algebnaly/nfs4c/NFS4FileWriteResult";
+use crate::interrupt::wait_interruptibly;
+use crate::jni_utils::{as_class, jni_cache};
+use crate::opened_file::{FileHandle, file_handle, release_file_handle};
+use crate::read_ahead::{ReadAheadConfig, Take, spawn_prefetch};
+use crate::retry::{Retryable, call_interruptible, call_with_deadline};
+use crate::session::{SessionHandle, session_handle};
+use crate::write_behind::{WriteBehindConfig, spawn_flush_timer};
 
 #[allow(non_snake_case)]
 #[unsafe(no_mangle)]
@@ -27,15 +36,13 @@ pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_fileRead(
     offset: jlong,
     byte_buffer: JObject,
 ) -> jobject {
-    let session_ptr = session as *mut NFSClientSession;
-    let session_ref: &mut NFSClientSession = unsafe { &mut *session_ptr };
+    let session_handle = unsafe { session_handle(session) };
 
-    let opened_file_ptr = opened_file as *mut OpenedFile;
-    let opened_file_ref: &mut OpenedFile = unsafe { &mut *opened_file_ptr };
+    let file_handle = unsafe { file_handle(opened_file) };
 
     match read_file(
-        session_ref,
-        opened_file_ref,
+        session_handle,
+        &file_handle,
         &byte_buffer,
         offset as usize,
         &mut env,
@@ -48,36 +55,60 @@ pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_fileRead(
     }
 }
 
-fn read_file(
-    session_ref: &mut NFSClientSession,
-    opened_file_ref: &mut OpenedFile,
+pub fn read_file(
+    session_handle: &'static SessionHandle,
+    file_handle: &Arc<FileHandle>,
     byte_buffer: &JObject, // ByteBuffer
     offset: usize,
     env: &mut JNIEnv,
 ) -> Result<jobject, NfscrsJniError> {
-    tracing::debug!("read_file: {:?}", opened_file_ref.path);
-    let buf_remaining = env
-        .call_method(&byte_buffer, "remaining", "()I", &[])
-        .and_then(|v| v.i())?;
-    let read_result = session_ref.read(opened_file_ref, offset, buf_remaining as usize)?;
-    let nfs4_file_read_result_class = env.find_class(NFS4_FILE_READ_RESULT_CLASS_NAME)?;
-    let count = read_result.data.len() as jint;
-    let result_obj = env.new_object(
-        nfs4_file_read_result_class,
-        "(ZI)V",
-        &[JValue::from(read_result.eof), JValue::from(count)],
-    )?;
-
-    let byte_array = env.byte_array_from_slice(&read_result.data)?;
-
-    env.call_method(
-        &byte_buffer,
-        "put",
-        "([B)Ljava/nio/ByteBuffer;",
-        &[JValue::Object(&byte_array.into())],
-    )?;
+    // Checked before reading, so that the cached or fetched data is not
+    // consumed for a buffer it cannot go into.
+    if buffer_is_read_only(env, byte_buffer)? {
+        return Err(NfscrsJniError::ReadOnlyBuffer);
+    }
+    let buf_remaining = buffer_remaining(env, byte_buffer)? as usize;
+    tracing::debug!("read_file: offset {offset} len {buf_remaining}");
+
+    let policy = session_handle.policy();
+    let cached = wait_interruptibly(env, "fileRead", policy.timeout, |wait| {
+        match file_handle.read_ahead.take(offset, buf_remaining, wait) {
+            Take::Hit(cached) => Some(Ok(Some(cached))),
+            Take::Miss => Some(Ok(None)),
+            Take::Pending => None,
+        }
+    })?;
+    let (data, eof) = match cached {
+        Some(cached) => (cached.data, cached.eof),
+        None => {
+            let file_handle = file_handle.clone();
+            // READs are safe to abandon, so a read is always interruptible.
+            call_interruptible(env, session_handle, "fileRead", move |session_ref| {
+                let mut opened_file_ref = file_handle.lock();
+                file_handle
+                    .write_behind
+                    .flush(session_ref, &mut opened_file_ref)?;
+                let io_sizes = file_handle.io_sizes(session_ref, &opened_file_ref)?;
+                let read_result = read_chunked(
+                    session_ref,
+                    &mut opened_file_ref,
+                    offset,
+                    buf_remaining,
+                    io_sizes.max_read,
+                )?;
+                Ok((read_result.data, read_result.eof))
+            })?
+        }
+    };
+    if let Some(request) = file_handle.read_ahead.record_read(offset, data.len(), eof) {
+        spawn_prefetch(session_handle, file_handle.clone(), request);
+    }
 
-    tracing::debug!("read_file ok : {:?}", opened_file_ref.path);
+    put_into_buffer(env, byte_buffer, &data)?;
+
+    let result_obj = new_read_result(env, eof, data.len() as jint)?;
+
+    tracing::debug!("read_file ok : offset {offset} count {}", data.len());
     return Ok(result_obj.into_raw());
 }
 
@@ -91,15 +122,13 @@ pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_fileWrite(
     offset: jlong,
     byte_buffer: JObject,
 ) -> jobject {
-    let session_ptr = session as *mut NFSClientSession;
-    let session_ref: &mut NFSClientSession = unsafe { &mut *session_ptr };
+    let session_handle = unsafe { session_handle(session) };
 
-    let opened_file_ptr = opened_file as *mut OpenedFile;
-    let opened_file_ref: &mut OpenedFile = unsafe { &mut *opened_file_ptr };
+    let file_handle = unsafe { file_handle(opened_file) };
 
     match write_file(
-        session_ref,
-        opened_file_ref,
+        session_handle,
+        &file_handle,
         &byte_buffer,
         offset as usize,
         &mut env,
@@ -112,56 +141,259 @@ pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_fileWrite(
     }
 }
 
-fn write_file(
-    session_ref: &mut NFSClientSession,
-    opened_file_ref: &mut OpenedFile,
+pub fn write_file(
+    session_handle: &'static SessionHandle,
+    file_handle: &Arc<FileHandle>,
     byte_buffer: &JObject, // ByteBuffer
     offset: usize,
     env: &mut JNIEnv,
 ) -> Result<jobject, NfscrsJniError> {
-    tracing::debug!("write_file: {:?}", opened_file_ref.path);
+    // Owned, since the RPC may run on a helper thread past this call.
+    let data = buffer_bytes(env, byte_buffer)?.into_owned();
+    if data.is_empty() {
+        return Ok(new_write_result(env, 0)?.into_raw());
+    }
+
+    file_handle.read_ahead.invalidate();
+    let writer = file_handle.clone();
+    let count = call_with_deadline(env, session_handle, "fileWrite", move |session_ref| {
+        let mut opened_file_ref = writer.lock();
+        tracing::debug!("write_file: {:?}", opened_file_ref.path);
+        // A retry after a failed threshold flush buffers `data` again; it
+        // is then written twice at the same offset, which is harmless.
+        let buffered =
+            writer
+                .write_behind
+                .buffer(session_ref, &mut opened_file_ref, offset, &data)?;
+        if buffered {
+            Ok(data.len() as jint)
+        } else {
+            let io_sizes = writer.io_sizes(session_ref, &opened_file_ref)?;
+            Ok(write_chunked(
+                session_ref,
+                &mut opened_file_ref,
+                offset,
+                &data,
+                io_sizes.max_write,
+            )? as jint)
+        }
+    })?;
+    if let Some(delay) = file_handle.write_behind.claim_timer() {
+        spawn_flush_timer(session_handle, file_handle.clone(), delay);
+    }
+    advance_buffer(env, byte_buffer, count)?;
 
-    let limit = env
-        .call_method(byte_buffer, "limit", "()I", &[])
-        .and_then(|v| v.i())?;
+    let result_obj = new_write_result(env, count)?;
 
-    if limit <= 0 {
-        let nfs4_file_write_result_class = env.find_class(NFS4_FILE_WRITE_RESULT_CLASS_NAME)?;
-        let result_obj =
-            env.new_object(nfs4_file_write_result_class, "(I)V", &[JValue::from(0)])?;
-        return Ok(result_obj.into_raw());
+    tracing::debug!("write_file ok : offset {offset} count {count}");
+    return Ok(result_obj.into_raw());
+}
+
+/// Scatter read into `byteBuffers`. Returns the number of bytes read as a
+/// `long`, since the buffers may hold more than 2 GiB together, or -1 at
+/// end of file when nothing was read (as `ScatteringByteChannel.read`).
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_fileReadv(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    opened_file: jlong,
+    offset: jlong,
+    byte_buffers: JObjectArray,
+) -> jlong {
+    let session_handle = unsafe { session_handle(session) };
+
+    let file_handle = unsafe { file_handle(opened_file) };
+
+    match read_file_vectored(
+        session_handle,
+        &file_handle,
+        &byte_buffers,
+        offset as usize,
+        &mut env,
+    ) {
+        Ok(r) => r,
+        Err(e) => {
+            handle_error(&mut env, &e);
+            return -1;
+        }
     }
+}
 
-    let slice: Vec<u8>;
-    let slice_ref: &[u8] = if let Ok(addr) = env.get_direct_buffer_address(byte_buffer.into()) {
-        // 直接引用 DirectBuffer 数据
-        unsafe { std::slice::from_raw_parts(addr, limit as usize) }
-    } else {
-        // fallback：普通 Heap ByteBuffer
-        slice = {
-            let mut v = vec![0u8; limit as usize];
-            let byte_array_obj = env.call_method(byte_buffer, "array", "()[B", &[])?.l()?;
-            let v_i8: &mut [i8] =
-                unsafe { std::slice::from_raw_parts_mut(v.as_mut_ptr() as *mut i8, v.len()) };
-            let byte_array = JByteArray::from(byte_array_obj);
-            env.get_byte_array_region(byte_array, 0, v_i8)?;
-            v
-        };
-        &slice
-    };
+/// Scatter read: the total `remaining()` of all buffers is read in
+/// maxread-sized READs and distributed across them in order.
+fn read_file_vectored(
+    session_handle: &'static SessionHandle,
+    file_handle: &Arc<FileHandle>,
+    byte_buffers: &JObjectArray, // ByteBuffer[]
+    offset: usize,
+    env: &mut JNIEnv,
+) -> Result<jlong, NfscrsJniError> {
+    let len = env.get_array_length(byte_buffers)?;
+    let mut remainings = Vec::with_capacity(len as usize);
+    for i in 0..len {
+        let buffer = env.get_object_array_element(byte_buffers, i)?;
+        if buffer_is_read_only(env, &buffer)? {
+            return Err(NfscrsJniError::ReadOnlyBuffer);
+        }
+        remainings.push(buffer_remaining(env, &buffer)? as usize);
+        env.delete_local_ref(buffer)?;
+    }
+    let total: usize = remainings.iter().sum();
+
+    let reader = file_handle.clone();
+    let read_result = call_interruptible(env, session_handle, "fileReadv", move |session_ref| {
+        let mut opened_file_ref = reader.lock();
+        tracing::debug!("read_file_vectored: {:?}", opened_file_ref.path);
+        reader
+            .write_behind
+            .flush(session_ref, &mut opened_file_ref)?;
+        let io_sizes = reader.io_sizes(session_ref, &opened_file_ref)?;
+        Ok(read_chunked(
+            session_ref,
+            &mut opened_file_ref,
+            offset,
+            total,
+            io_sizes.max_read,
+        )?)
+    })?;
+    let mut data: &[u8] = &read_result.data;
+    for (i, remaining) in (0..len).zip(remainings) {
+        if data.is_empty() {
+            break;
+        }
+        let (head, tail) = data.split_at(remaining.min(data.len()));
+        let buffer = env.get_object_array_element(byte_buffers, i)?;
+        put_into_buffer(env, &buffer, head)?;
+        env.delete_local_ref(buffer)?;
+        data = tail;
+    }
+
+    let count = read_result.data.len();
+    tracing::debug!("read_file_vectored ok : offset {offset} count {count}");
+    if count == 0 && read_result.eof {
+        return Ok(-1);
+    }
+    Ok(count as jlong)
+}
 
-    let write_result = session_ref.write(opened_file_ref, offset, slice_ref)?;
+/// Gather write from `byteBuffers`. Returns the number of bytes written as
+/// a `long`.
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_fileWritev(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    opened_file: jlong,
+    offset: jlong,
+    byte_buffers: JObjectArray,
+) -> jlong {
+    let session_handle = unsafe { session_handle(session) };
 
-    let nfs4_file_write_result_class = env.find_class(NFS4_FILE_WRITE_RESULT_CLASS_NAME)?;
-    let count = write_result.count;
-    let result_obj = env.new_object(
-        nfs4_file_write_result_class,
-        "(I)V",
-        &[JValue::from(count as i32)],
-    )?;
+    let file_handle = unsafe { file_handle(opened_file) };
 
-    tracing::debug!("write_file ok : {:?}", opened_file_ref.path);
-    return Ok(result_obj.into_raw());
+    match write_file_vectored(
+        session_handle,
+        &file_handle,
+        &byte_buffers,
+        offset as usize,
+        &mut env,
+    ) {
+        Ok(r) => r,
+        Err(e) => {
+            handle_error(&mut env, &e);
+            return -1;
+        }
+    }
+}
+
+/// Gather write: the `remaining()` bytes of all buffers are sent in
+/// maxwrite-sized WRITEs, and the written count is consumed from the buffers
+/// in order.
+fn write_file_vectored(
+    session_handle: &'static SessionHandle,
+    file_handle: &Arc<FileHandle>,
+    byte_buffers: &JObjectArray, // ByteBuffer[]
+    offset: usize,
+    env: &mut JNIEnv,
+) -> Result<jlong, NfscrsJniError> {
+    let len = env.get_array_length(byte_buffers)?;
+    let mut data: Vec<u8> = Vec::new();
+    let mut lengths = Vec::with_capacity(len as usize);
+    for i in 0..len {
+        let buffer = env.get_object_array_element(byte_buffers, i)?;
+        {
+            let bytes = buffer_bytes(env, &buffer)?;
+            lengths.push(bytes.len());
+            data.extend_from_slice(&bytes);
+        }
+        env.delete_local_ref(buffer)?;
+    }
+    if data.is_empty() {
+        return Ok(0);
+    }
+
+    file_handle.read_ahead.invalidate();
+    let writer = file_handle.clone();
+    let count = call_with_deadline(env, session_handle, "fileWritev", move |session_ref| {
+        let mut opened_file_ref = writer.lock();
+        tracing::debug!("write_file_vectored: {:?}", opened_file_ref.path);
+        writer
+            .write_behind
+            .flush(session_ref, &mut opened_file_ref)?;
+        let io_sizes = writer.io_sizes(session_ref, &opened_file_ref)?;
+        Ok(write_chunked(
+            session_ref,
+            &mut opened_file_ref,
+            offset,
+            &data,
+            io_sizes.max_write,
+        )?)
+    })?;
+    let mut left = count;
+    for (i, buffer_len) in (0..len).zip(lengths) {
+        if left == 0 {
+            break;
+        }
+        let consumed = buffer_len.min(left);
+        let buffer = env.get_object_array_element(byte_buffers, i)?;
+        advance_buffer(env, &buffer, consumed as jint)?;
+        env.delete_local_ref(buffer)?;
+        left -= consumed;
+    }
+
+    tracing::debug!("write_file_vectored ok : offset {offset} count {count}");
+    Ok(count as jlong)
+}
+
+fn new_read_result<'a>(
+    env: &mut JNIEnv<'a>,
+    eof: bool,
+    count: jint,
+) -> Result<JObject<'a>, NfscrsJniError> {
+    let cache = jni_cache()?;
+    let obj = unsafe {
+        env.new_object_unchecked(
+            as_class(&cache.read_result_class),
+            cache.read_result_ctor,
+            &[JValue::from(eof).as_jni(), JValue::from(count).as_jni()],
+        )
+    }?;
+    Ok(obj)
+}
+
+fn new_write_result<'a>(env: &mut JNIEnv<'a>, count: jint) -> Result<JObject<'a>, NfscrsJniError> {
+    let cache = jni_cache()?;
+    let obj = unsafe {
+        env.new_object_unchecked(
+            as_class(&cache.write_result_class),
+            cache.write_result_ctor,
+            &[JValue::from(count).as_jni()],
+        )
+    }?;
+    Ok(obj)
 }
 
 #[allow(non_snake_case)]
@@ -172,15 +404,13 @@ pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_fileClose(
     session: jlong,
     opened_file: jlong,
 ) {
-    let session_ptr = session as *mut NFSClientSession;
-    let session_ref: &mut NFSClientSession = unsafe { &mut *session_ptr };
+    let session_handle = unsafe { session_handle(session) };
 
-    let opened_file_ptr = opened_file as *mut OpenedFile;
-    let opened_file_ref: &mut OpenedFile = unsafe { &mut *opened_file_ptr };
-    match close_file(session_ref, opened_file_ref) {
+    let file_handle = unsafe { file_handle(opened_file) };
+    match close_file(&mut env, session_handle, &file_handle) {
         Ok(_r) => {
             unsafe {
-                let _ = Box::from_raw(opened_file_ptr); // release opened file
+                release_file_handle(opened_file); // release opened file
             }
         }
         Err(e) => {
@@ -189,14 +419,176 @@ pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_fileClose(
     }
 }
 
-fn close_file(
-    session_ref: &mut NFSClientSession,
-    opened_file_ref: &mut OpenedFile,
+pub fn close_file(
+    env: &mut JNIEnv,
+    session_handle: &'static SessionHandle,
+    file_handle: &Arc<FileHandle>,
 ) -> Result<(), NfscrsJniError> {
-    tracing::debug!("close_file: {:?}", opened_file_ref.path);
-    session_ref.close(opened_file_ref)?;
-    tracing::debug!("close_file ok : {:?}", opened_file_ref.path);
-    Ok(())
+    // Before the session lock, which a running prefetch is waiting for.
+    file_handle.read_ahead.wait_idle();
+    let file_handle = file_handle.clone();
+    call_with_deadline(env, session_handle, "fileClose", move |session_ref| {
+        let mut opened_file_ref = file_handle.lock();
+        tracing::debug!("close_file: {:?}", opened_file_ref.path);
+        let flush_result = file_handle
+            .write_behind
+            .flush(session_ref, &mut opened_file_ref);
+        // A flush the server asked to delay is retried before closing.
+        if flush_result.as_ref().is_err_and(|e| e.is_retryable()) {
+            flush_result?;
+        }
+        // Otherwise the file is closed even when deferred writes failed.
+        // Their error came first and is the one reported.
+        let close_result = session_ref.close(&mut opened_file_ref);
+        if let (Err(_), Err(e)) = (&flush_result, &close_result) {
+            tracing::debug!("close_file: close failed after a failed flush: {e}");
+        }
+        flush_result?;
+        close_result?;
+        tracing::debug!("close_file ok : {:?}", opened_file_ref.path);
+        Ok(())
+    })
+}
+
+/// Configures read-ahead for an opened file. `max_window` of zero disables it;
+/// otherwise `min_window` must be at least 1.
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_fileSetReadAhead(
+    mut env: JNIEnv,
+    _this: JObject,
+    opened_file: jlong,
+    min_window: jint,
+    max_window: jint,
+) {
+    if min_window < 0
+        || max_window < 0
+        || (max_window > 0 && (min_window == 0 || min_window > max_window))
+    {
+        let e = NfscrsJniError::IllegalArgument(format!(
+            "invalid read-ahead window: min {min_window}, max {max_window}"
+        ));
+        handle_error(&mut env, &e);
+        return;
+    }
+    let file_handle = unsafe { file_handle(opened_file) };
+    file_handle.read_ahead.set_config(ReadAheadConfig {
+        min_window: min_window as usize,
+        max_window: max_window as usize,
+    });
+}
+
+/// Enables write-behind buffering for an opened file. Buffered data is sent
+/// in maxwrite-sized chunks once `threshold` bytes are pending (zero means
+/// maxwrite), or after `flush_delay_millis` without writes (zero disables the
+/// timer).
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_fileSetWriteBehind(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    opened_file: jlong,
+    enabled: jboolean,
+    threshold: jint,
+    flush_delay_millis: jint,
+) {
+    let session_handle = unsafe { session_handle(session) };
+    let file_handle = unsafe { file_handle(opened_file) };
+    match set_write_behind(
+        &mut env,
+        session_handle,
+        &file_handle,
+        enabled != 0,
+        threshold,
+        flush_delay_millis,
+    ) {
+        Ok(_) => {}
+        Err(e) => {
+            handle_error(&mut env, &e);
+        }
+    }
+}
+
+fn set_write_behind(
+    env: &mut JNIEnv,
+    session_handle: &'static SessionHandle,
+    file_handle: &Arc<FileHandle>,
+    enabled: bool,
+    threshold: jint,
+    flush_delay_millis: jint,
+) -> Result<(), NfscrsJniError> {
+    if threshold < 0 || flush_delay_millis < 0 {
+        return Err(NfscrsJniError::IllegalArgument(format!(
+            "invalid write-behind settings: threshold {threshold}, delay {flush_delay_millis}ms"
+        )));
+    }
+    let file_handle = file_handle.clone();
+    call_with_deadline(
+        env,
+        session_handle,
+        "fileSetWriteBehind",
+        move |session_ref| {
+            let mut opened_file_ref = file_handle.lock();
+            tracing::debug!("set_write_behind: {:?} {enabled}", opened_file_ref.path);
+            let config = if enabled {
+                let max_write = file_handle
+                    .io_sizes(session_ref, &opened_file_ref)?
+                    .max_write;
+                Some(WriteBehindConfig {
+                    chunk_size: max_write,
+                    threshold: if threshold == 0 {
+                        max_write
+                    } else {
+                        threshold as usize
+                    },
+                    flush_delay: Duration::from_millis(flush_delay_millis as u64),
+                })
+            } else {
+                None
+            };
+            file_handle
+                .write_behind
+                .set_config(session_ref, &mut opened_file_ref, config)?;
+            Ok(())
+        },
+    )
+}
+
+/// Flushes buffered writes of an opened file (`FileChannel.force`).
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_fileSync(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    opened_file: jlong,
+) {
+    let session_handle = unsafe { session_handle(session) };
+    let file_handle = unsafe { file_handle(opened_file) };
+    match sync_file(&mut env, session_handle, &file_handle) {
+        Ok(_) => {}
+        Err(e) => {
+            handle_error(&mut env, &e);
+        }
+    }
+}
+
+fn sync_file(
+    env: &mut JNIEnv,
+    session_handle: &'static SessionHandle,
+    file_handle: &Arc<FileHandle>,
+) -> Result<(), NfscrsJniError> {
+    let file_handle = file_handle.clone();
+    call_with_deadline(env, session_handle, "fileSync", move |session_ref| {
+        let mut opened_file_ref = file_handle.lock();
+        tracing::debug!("sync_file: {:?}", opened_file_ref.path);
+        file_handle
+            .write_behind
+            .flush(session_ref, &mut opened_file_ref)?;
+        tracing::debug!("sync_file ok : {:?}", opened_file_ref.path);
+        Ok(())
+    })
 }
 
 #[allow(non_snake_case)]
@@ -207,13 +599,10 @@ pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_fileSize(
     session: jlong,
     opened_file: jlong,
 ) -> jlong {
-    let session_ptr = session as *mut NFSClientSession;
-    let session_ref: &mut NFSClientSession = unsafe { &mut *session_ptr };
+    let session_handle = unsafe { session_handle(session) };
 
-    let opened_file_ptr = opened_file as *mut OpenedFile;
-    let opened_file_ref: &mut OpenedFile = unsafe { &mut *opened_file_ptr };
-    tracing::debug!("file_size: {:?}", opened_file_ref.path);
-    match get_file_size_from_opened_file(session_ref, opened_file_ref) {
+    let file_handle = unsafe { file_handle(opened_file) };
+    match get_file_size_from_opened_file(&mut env, session_handle, &file_handle) {
         Ok(r) => r,
         Err(e) => {
             handle_error(&mut env, &e);
@@ -222,13 +611,24 @@ pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_fileSize(
     }
 }
 
+/// Runs under a deadline rather than interruptibly, since it flushes
+/// buffered writes first.
 fn get_file_size_from_opened_file(
-    session_ref: &mut NFSClientSession,
-    opened_file_ref: &mut OpenedFile,
+    env: &mut JNIEnv,
+    session_handle: &'static SessionHandle,
+    file_handle: &Arc<FileHandle>,
 ) -> Result<i64, NfscrsJniError> {
-    let fattr4 = session_ref.get_attr(&opened_file_ref.path, basic_attr_bitmap())?;
-    tracing::debug!("file_size ok : {:?}", opened_file_ref.path);
-    crate::attr_utils::get_file_size(&fattr4).map(|size| size as i64)
+    let file_handle = file_handle.clone();
+    call_with_deadline(env, session_handle, "fileSize", move |session_ref| {
+        let mut opened_file_ref = file_handle.lock();
+        tracing::debug!("file_size: {:?}", opened_file_ref.path);
+        file_handle
+            .write_behind
+            .flush(session_ref, &mut opened_file_ref)?;
+        let fattr4 = session_ref.get_attr(&opened_file_ref.path, basic_attr_bitmap())?;
+        tracing::debug!("file_size ok : {:?}", opened_file_ref.path);
+        crate::attr_utils::get_file_size(&fattr4).map(|size| size as i64)
+    })
 }
 
 #[allow(non_snake_case)]
@@ -240,10 +640,9 @@ pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_openFile(
     path: JString,
     open_options: jint,
 ) -> jlong {
-    let session_ptr = session as *mut NFSClientSession;
-    let session_ref: &mut NFSClientSession = unsafe { &mut *session_ptr };
+    let session_handle = unsafe { session_handle(session) };
     let opts = int_to_open_options(open_options);
-    match open_file(session_ref, &mut env, &path, opts) {
+    match open_file(session_handle, &mut env, &path, opts) {
         Ok(r) => r,
         Err(e) => {
             handle_error(&mut env, &e);
@@ -252,20 +651,23 @@ pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_openFile(
     }
 }
 
-fn open_file(
-    session_ref: &mut NFSClientSession,
+/// An open abandoned at the deadline may still complete on the server; the
+/// file is then left open until the session ends.
+pub fn open_file(
+    session_handle: &'static SessionHandle,
     env: &mut JNIEnv,
     path: &JString,
     opts: OpenOptions,
 ) -> Result<jlong, NfscrsJniError> {
     let path_str: String = env.get_string(&path)?.into();
-    let abs_path = AbsolutePath::try_from(path_str).map_err(|e| NFSCRSError::InnerError(e))?;
-    tracing::debug!("open_file: {:?}", abs_path);
-    let opened_file = session_ref.open_file(&abs_path, opts)?;
-    let file = Box::new(opened_file);
-    let file_ptr = Box::into_raw(file);
-    tracing::debug!("open_file ok : {:?}", abs_path);
-    Ok(file_ptr as jlong)
+    call_with_deadline(env, session_handle, "openFile", move |session_ref| {
+        let abs_path = session_ref.resolve(path_str.clone())?;
+        tracing::debug!("open_file: {:?}", abs_path);
+        let opened_file = session_ref.open_file(&abs_path, opts.clone())?;
+        let file_ptr = FileHandle::new(opened_file).into_jlong();
+        tracing::debug!("open_file ok : {:?}", abs_path);
+        Ok(file_ptr)
+    })
 }
 
 #[allow(non_snake_case)]
@@ -279,10 +681,9 @@ pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_mkdir(
     parents: jboolean,
     exists_ok: jboolean,
 ) {
-    let session_ptr = session as *mut NFSClientSession;
-    let session_ref: &mut NFSClientSession = unsafe { &mut *session_ptr };
+    let session_handle = unsafe { session_handle(session) };
     let opts = int_to_open_options(open_options);
-    match mkdir(session_ref, &mut env, &path, opts, parents, exists_ok) {
+    match mkdir(session_handle, &mut env, &path, opts, parents, exists_ok) {
         Ok(r) => r,
         Err(e) => {
             handle_error(&mut env, &e);
@@ -291,7 +692,7 @@ pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_mkdir(
 }
 
 fn mkdir(
-    session_ref: &mut NFSClientSession,
+    session_handle: &'static SessionHandle,
     env: &mut JNIEnv,
     path: &JString,
     _opts: OpenOptions, // TODO: create dir with provided OpenOptions
@@ -299,11 +700,13 @@ fn mkdir(
     exists_ok: jboolean,
 ) -> Result<(), NfscrsJniError> {
     let path_str: String = env.get_string(&path)?.into();
-    let abs_path = AbsolutePath::try_from(path_str).map_err(|e| NFSCRSError::InnerError(e))?;
-    tracing::debug!("mkdir: {:?}", abs_path);
-    session_ref.mkdir(&abs_path, parents != 0, exists_ok != 0)?;
-    tracing::debug!("mkdir ok : {:?}", abs_path);
-    Ok(())
+    call_with_deadline(env, session_handle, "mkdir", move |session_ref| {
+        let abs_path = session_ref.resolve(path_str.clone())?;
+        tracing::debug!("mkdir: {:?}", abs_path);
+        session_ref.mkdir(&abs_path, parents != 0, exists_ok != 0)?;
+        tracing::debug!("mkdir ok : {:?}", abs_path);
+        Ok(())
+    })
 }
 
 #[allow(non_snake_case)]
@@ -318,10 +721,9 @@ pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_setFileTimes(
     ctime: jlong,
     bitmap: jint,
 ) -> jboolean {
-    let session_ptr = session as *mut NFSClientSession;
-    let session_ref: &mut NFSClientSession = unsafe { &mut *session_ptr };
+    let session_handle = unsafe { session_handle(session) };
 
-    match set_file_times(session_ref, &mut env, &path, mtime, atime, ctime, bitmap) {
+    match set_file_times(session_handle, &mut env, &path, mtime, atime, ctime, bitmap) {
         Ok(r) => r as jboolean,
         Err(e) => {
             handle_error(&mut env, &e);
@@ -331,7 +733,7 @@ pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_setFileTimes(
 }
 
 fn set_file_times(
-    _session_ref: &mut NFSClientSession,
+    session_handle: &'static SessionHandle,
     env: &mut JNIEnv,
     path: &JString,
     _mtime: jlong,
@@ -340,9 +742,13 @@ fn set_file_times(
     _bitmap: jint,
 ) -> Result<jlong, NfscrsJniError> {
     let path_str: String = env.get_string(&path)?.into();
-    let _abs_path = AbsolutePath::try_from(path_str).map_err(|e| NFSCRSError::InnerError(e))?;
-    tracing::debug!("set_files_times: {:?}", _abs_path);
-    todo!()
+    call_with_deadline(env, session_handle, "setFileTimes", move |session_ref| {
+        let abs_path = session_ref.resolve(path_str.clone())?;
+        tracing::debug!("set_files_times: {:?}", abs_path);
+        Err(NfscrsJniError::UnsupportedOperation(
+            "setFileTimes is not implemented".to_string(),
+        ))
+    })
 }
 
 #[allow(non_snake_case)]
@@ -353,9 +759,8 @@ pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_delete(
     session: jlong,
     path: JString,
 ) -> jboolean {
-    let session_ptr = session as *mut NFSClientSession;
-    let session_ref: &mut NFSClientSession = unsafe { &mut *session_ptr };
-    match path_delete(session_ref, &mut env, &path) {
+    let session_handle = unsafe { session_handle(session) };
+    match path_delete(session_handle, &mut env, &path) {
         Ok(r) => r,
         Err(e) => {
             handle_error(&mut env, &e);
@@ -366,18 +771,16 @@ pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_delete(
 
 #[allow(unused)]
 fn path_delete(
-    session_ref: &mut NFSClientSession,
+    session_handle: &'static SessionHandle,
     env: &mut JNIEnv,
     path: &JString,
 ) -> Result<jboolean, NfscrsJniError> {
     let path_str: String = env.get_string(&path)?.into();
-    let abs_path = AbsolutePath::try_from(path_str).map_err(|e| NFSCRSError::InnerError(e))?;
-    tracing::debug!("path_delete: {:?}", abs_path);
-
-    todo!()
-    // let opened_file = session_ref.open_file(&abs_path, opts)?;
-    // let file = Box::new(opened_file);
-    // let file_ptr = Box::into_raw(file);
-    // tracing::debug!("open_file ok : {:?}", abs_path);
-    // Ok(file_ptr as jlong)
+    call_with_deadline(env, session_handle, "delete", move |session_ref| {
+        let abs_path = session_ref.resolve(path_str.clone())?;
+        tracing::debug!("path_delete: {:?}", abs_path);
+        Err(NfscrsJniError::UnsupportedOperation(
+            "delete is not implemented".to_string(),
+        ))
+    })
 }
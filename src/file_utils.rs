@@ -12,3 +12,20 @@ pub fn int_to_open_options(i: i32) -> OpenOptions {
         .create((i & 1 << CREATE_BIT_NUM) != 0)
         .truncate((i & 1 << TRUNCATE_BIT_NUM) != 0)
 }
+
+pub const COPY_REPLACE_EXISTING_BIT_NUM: usize = 0;
+pub const COPY_CLIENT_SIDE_ONLY_BIT_NUM: usize = 1;
+
+#[derive(Debug, Clone, Copy)]
+pub struct CopyOptions {
+    pub replace_existing: bool,
+    /// Skip CLONE and COPY and always copy through the client.
+    pub client_side_only: bool,
+}
+
+pub fn int_to_copy_options(i: i32) -> CopyOptions {
+    CopyOptions {
+        replace_existing: (i & 1 << COPY_REPLACE_EXISTING_BIT_NUM) != 0,
+        client_side_only: (i & 1 << COPY_CLIENT_SIDE_ONLY_BIT_NUM) != 0,
+    }
+}
@@ -0,0 +1,242 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, SendError, Sender, channel};
+use std::time::{Duration, Instant};
+
+use jni::JNIEnv;
+use jni::objects::JObject;
+use jni::signature::{Primitive, ReturnType};
+
+use crate::error::NfscrsJniError;
+use crate::jni_utils::{as_class, jni_cache};
+
+/// How often a waiting caller looks at its interrupt flag.
+const INTERRUPT_POLL: Duration = Duration::from_millis(50);
+
+/// Helper threads exit after being idle this long.
+const HELPER_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+type Task = Box<dyn FnOnce() + Send>;
+
+/// Idle helper threads, each waiting on its own channel, with an id so an
+/// expiring helper can find itself.
+static IDLE_HELPERS: Mutex<Vec<(u64, Sender<Task>)>> = Mutex::new(Vec::new());
+
+static NEXT_HELPER_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Runs `op` on a helper thread while the calling Java thread waits, and
+/// gives up with `Interrupted` (`ClosedByInterruptException`) as soon as the
+/// caller is interrupted, or with `Timeout` (`SocketTimeoutException`) once
+/// `timeout` has passed. The interrupt flag is left set, as NIO channels do.
+///
+/// Helper threads are reused across calls; a new one is only started when
+/// all are busy, e.g. with operations that were abandoned.
+///
+/// An abandoned `op` is not cut short: it runs on the helper thread, still
+/// holding the session lock, and its reply is dropped. It is bounded by the
+/// RPC timeout that `SessionHandle::lock` installs from the same policy, so
+/// a hung server makes it fail soon after the caller gave up, releasing the
+/// lock and the helper. Without a timeout only an interrupt abandons the
+/// wait, and the helper stays with the RPC until it returns. `op` must be
+/// safe to finish unobserved; operations that create state (opens, writes)
+/// are only run this way under a deadline (see `with_deadline`), where an
+/// unknown outcome is what a timeout means.
+pub fn interruptible<T, F>(
+    env: &mut JNIEnv,
+    name: &str,
+    timeout: Option<Duration>,
+    op: F,
+) -> Result<T, NfscrsJniError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, NfscrsJniError> + Send + 'static,
+{
+    let thread = current_thread(env)?;
+    let result = wait_on_helper(env, &thread, name, timeout, op);
+    env.delete_local_ref(thread)?;
+    result
+}
+
+fn wait_on_helper<T, F>(
+    env: &mut JNIEnv,
+    thread: &JObject,
+    name: &str,
+    timeout: Option<Duration>,
+    op: F,
+) -> Result<T, NfscrsJniError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, NfscrsJniError> + Send + 'static,
+{
+    if is_interrupted(env, thread)? {
+        return Err(NfscrsJniError::Interrupted(format!("{name}: not started")));
+    }
+    let deadline = timeout.map(|t| Instant::now() + t);
+    let (sender, receiver) = channel();
+    run_on_helper(Box::new(move || {
+        // The receiver is gone when the caller gave up.
+        let _ = sender.send(op());
+    }))?;
+    poll_until(
+        env,
+        thread,
+        name,
+        timeout,
+        deadline,
+        |wait| match receiver.recv_timeout(wait) {
+            Ok(result) => Some(result),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => Some(Err(NfscrsJniError::NFSCRSJNIError(
+                format!("{name}: helper thread panicked"),
+            ))),
+        },
+    )
+}
+
+/// Waits on the calling Java thread for `poll` to produce a result, with
+/// the same interrupt and timeout behaviour as `interruptible`. `poll` is
+/// given how long it may block and returns `None` when nothing is ready.
+/// For waits on work that is already running elsewhere, such as a prefetch.
+pub fn wait_interruptibly<T>(
+    env: &mut JNIEnv,
+    name: &str,
+    timeout: Option<Duration>,
+    mut poll: impl FnMut(Duration) -> Option<Result<T, NfscrsJniError>>,
+) -> Result<T, NfscrsJniError> {
+    // Most waits are over at once, without looking up the thread.
+    if let Some(result) = poll(Duration::ZERO) {
+        return result;
+    }
+    let deadline = timeout.map(|t| Instant::now() + t);
+    let thread = current_thread(env)?;
+    let result = poll_until(env, &thread, name, timeout, deadline, poll);
+    env.delete_local_ref(thread)?;
+    result
+}
+
+fn poll_until<T>(
+    env: &mut JNIEnv,
+    thread: &JObject,
+    name: &str,
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+    mut poll: impl FnMut(Duration) -> Option<Result<T, NfscrsJniError>>,
+) -> Result<T, NfscrsJniError> {
+    loop {
+        let wait = match deadline {
+            Some(deadline) => deadline
+                .saturating_duration_since(Instant::now())
+                .min(INTERRUPT_POLL),
+            None => INTERRUPT_POLL,
+        };
+        if let Some(result) = poll(wait) {
+            return result;
+        }
+        if is_interrupted(env, thread)? {
+            return Err(NfscrsJniError::Interrupted(format!(
+                "{name}: abandoned, the reply will be discarded"
+            )));
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Err(NfscrsJniError::Timeout(format!(
+                "{name} timed out after {}ms",
+                timeout.unwrap_or_default().as_millis()
+            )));
+        }
+    }
+}
+
+/// Hands `task` to an idle helper thread, starting one if there is none.
+fn run_on_helper(mut task: Task) -> Result<(), NfscrsJniError> {
+    loop {
+        let idle = IDLE_HELPERS.lock().unwrap_or_else(|e| e.into_inner()).pop();
+        let Some((_, helper)) = idle else {
+            break;
+        };
+        match helper.send(task) {
+            Ok(()) => return Ok(()),
+            // The helper panicked in its previous task.
+            Err(SendError(returned)) => task = returned,
+        }
+    }
+    let id = NEXT_HELPER_ID.fetch_add(1, Ordering::Relaxed);
+    let (sender, receiver) = channel::<Task>();
+    let _ = sender.send(task);
+    std::thread::Builder::new()
+        .name(format!("nfscrs-helper-{id}"))
+        .spawn(move || helper_loop(id, sender, receiver))?;
+    Ok(())
+}
+
+fn helper_loop(id: u64, sender: Sender<Task>, receiver: Receiver<Task>) {
+    loop {
+        match receiver.recv_timeout(HELPER_IDLE_TIMEOUT) {
+            Ok(task) => {
+                task();
+                IDLE_HELPERS
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .push((id, sender.clone()));
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                let mut idle = IDLE_HELPERS.lock().unwrap_or_else(|e| e.into_inner());
+                match idle.iter().position(|(helper_id, _)| *helper_id == id) {
+                    Some(i) => {
+                        idle.swap_remove(i);
+                        return;
+                    }
+                    // Taken by a caller that is about to send a task.
+                    None => continue,
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+/// Runs `op` inline when there is no deadline, otherwise as `interruptible`.
+/// For operations that create state (opens, writes), which are only worth
+/// abandoning when the caller asked for a bound on the wait.
+pub fn with_deadline<T, F>(
+    env: &mut JNIEnv,
+    name: &str,
+    timeout: Option<Duration>,
+    op: F,
+) -> Result<T, NfscrsJniError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, NfscrsJniError> + Send + 'static,
+{
+    match timeout {
+        None => op(),
+        Some(_) => interruptible(env, name, timeout, op),
+    }
+}
+
+/// `Thread.currentThread()`, looked up once per call rather than per poll.
+fn current_thread<'local>(env: &mut JNIEnv<'local>) -> Result<JObject<'local>, NfscrsJniError> {
+    let cache = jni_cache()?;
+    Ok(unsafe {
+        env.call_static_method_unchecked(
+            as_class(&cache.thread_class),
+            cache.thread_current_thread,
+            ReturnType::Object,
+            &[],
+        )
+    }?
+    .l()?)
+}
+
+/// `thread.isInterrupted()`, without clearing the flag.
+fn is_interrupted(env: &mut JNIEnv, thread: &JObject) -> Result<bool, NfscrsJniError> {
+    let cache = jni_cache()?;
+    Ok(unsafe {
+        env.call_method_unchecked(
+            thread,
+            cache.thread_is_interrupted,
+            ReturnType::Primitive(Primitive::Boolean),
+            &[],
+        )
+    }?
+    .z()?)
+}
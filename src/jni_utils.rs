@@ -1,2 +1,258 @@
+use std::sync::OnceLock;
+
+use jni::JNIEnv;
+use jni::objects::{GlobalRef, JClass, JMethodID, JStaticMethodID};
+
+use crate::error::NfscrsJniError;
+
 #[allow(unused)]
 pub const CTOR_NAME: &str = "<init>";
+
+const FILETIME_CLASS_NAME: &str = "java/nio/file/attribute/FileTime";
+const NFS4_FILE_ATTRIBUTES_CLASS_NAME: &str = "com/algebnaly/nfs4c/NFS4FileAttributes";
+const NFS4_FILE_READ_RESULT_CLASS_NAME: &str = "com/algebnaly/nfs4c/NFS4FileReadResult";
+const NFS4_FILE_WRITE_RESULT_CLASS_NAME: &str = "com/algebnaly/nfs4c/NFS4FileWriteResult";
+const NFS4_EXPORT_INFO_CLASS_NAME: &str = "com/algebnaly/nfs4c/NFS4ExportInfo";
+const NFS4_USER_PRINCIPAL_CLASS_NAME: &str = "com/algebnaly/nfs4c/NFS4UserPrincipal";
+const NFS4_GROUP_PRINCIPAL_CLASS_NAME: &str = "com/algebnaly/nfs4c/NFS4GroupPrincipal";
+
+pub const NFS4_FILE_ATTRIBUTES_CTOR_SIG: &str = "(Ljava/nio/file/attribute/FileTime;Ljava/nio/file/attribute/FileTime;Ljava/nio/file/attribute/FileTime;ZZZZJILjava/lang/Object;)V";
+
+/// Classes and method IDs resolved once in `JNI_OnLoad`.
+///
+/// Looking these up on every call is expensive (especially for `listDir` on
+/// large directories), and `find_class` on a native-attached thread would not
+/// see application classes anyway. The cache lives until the process exits:
+/// natives on other threads may still hold it when the library is unloaded.
+pub struct JniCache {
+    pub filetime_class: GlobalRef,
+    pub filetime_from_millis: JStaticMethodID,
+    pub nfs4_file_attributes_class: GlobalRef,
+    pub nfs4_file_attributes_ctor: JMethodID,
+    pub read_result_class: GlobalRef,
+    pub read_result_ctor: JMethodID,
+    pub write_result_class: GlobalRef,
+    pub write_result_ctor: JMethodID,
+    pub export_info: OptionalClass,
+    /// `UserPrincipal` built from an NFSv4 owner name.
+    pub user_principal: OptionalClass,
+    /// `GroupPrincipal` built from an NFSv4 owner group name.
+    pub group_principal: OptionalClass,
+    pub posix_file_permissions_class: GlobalRef,
+    pub posix_file_permissions_from_string: JStaticMethodID,
+    pub array_list_class: GlobalRef,
+    pub array_list_ctor: JMethodID,
+    pub array_list_add: JMethodID,
+    pub hash_map_class: GlobalRef,
+    pub hash_map_ctor: JMethodID,
+    pub hash_map_put: JMethodID,
+    pub long_class: GlobalRef,
+    pub long_value_of: JStaticMethodID,
+    pub integer_class: GlobalRef,
+    pub integer_value_of: JStaticMethodID,
+    pub boolean_class: GlobalRef,
+    pub boolean_value_of: JStaticMethodID,
+    pub buffer_class: GlobalRef,
+    pub buffer_position: JMethodID,
+    pub buffer_set_position: JMethodID,
+    pub buffer_remaining: JMethodID,
+    pub buffer_is_read_only: JMethodID,
+    pub read_only_buffer_exception_class: GlobalRef,
+    pub read_only_buffer_exception_ctor: JMethodID,
+    pub closed_by_interrupt_exception_class: GlobalRef,
+    pub closed_by_interrupt_exception_ctor: JMethodID,
+    pub uri_syntax_exception_class: GlobalRef,
+    pub uri_syntax_exception_ctor: JMethodID,
+    pub thread_class: GlobalRef,
+    pub thread_current_thread: JStaticMethodID,
+    pub thread_is_interrupted: JMethodID,
+}
+
+static JNI_CACHE: OnceLock<JniCache> = OnceLock::new();
+
+/// An application class that only some natives need, resolved on first use
+/// so that `JNI_OnLoad` does not fail when it is missing. That first use
+/// must be on a Java thread, where `find_class` sees application classes.
+pub struct OptionalClass {
+    name: &'static str,
+    ctor_sig: &'static str,
+    resolved: OnceLock<(GlobalRef, JMethodID)>,
+}
+
+impl OptionalClass {
+    const fn new(name: &'static str, ctor_sig: &'static str) -> OptionalClass {
+        OptionalClass {
+            name,
+            ctor_sig,
+            resolved: OnceLock::new(),
+        }
+    }
+
+    /// The class and its constructor, resolving them on first use. A missing
+    /// class leaves `NoClassDefFoundError` pending.
+    pub fn get(&self, env: &mut JNIEnv) -> Result<(&JClass<'static>, JMethodID), NfscrsJniError> {
+        if let Some((class, ctor)) = self.resolved.get() {
+            return Ok((as_class(class), *ctor));
+        }
+        let class = env.find_class(self.name)?;
+        let ctor = env.get_method_id(&class, CTOR_NAME, self.ctor_sig)?;
+        let global = env.new_global_ref(&class)?;
+        env.delete_local_ref(class)?;
+        let (class, ctor) = self.resolved.get_or_init(|| (global, ctor));
+        Ok((as_class(class), *ctor))
+    }
+}
+
+impl JniCache {
+    fn resolve(env: &mut JNIEnv) -> Result<JniCache, NfscrsJniError> {
+        let filetime_class = env.find_class(FILETIME_CLASS_NAME)?;
+        let filetime_from_millis = env.get_static_method_id(
+            &filetime_class,
+            "fromMillis",
+            "(J)Ljava/nio/file/attribute/FileTime;",
+        )?;
+
+        let nfs4_file_attributes_class = env.find_class(NFS4_FILE_ATTRIBUTES_CLASS_NAME)?;
+        let nfs4_file_attributes_ctor = env.get_method_id(
+            &nfs4_file_attributes_class,
+            CTOR_NAME,
+            NFS4_FILE_ATTRIBUTES_CTOR_SIG,
+        )?;
+
+        let read_result_class = env.find_class(NFS4_FILE_READ_RESULT_CLASS_NAME)?;
+        let read_result_ctor = env.get_method_id(&read_result_class, CTOR_NAME, "(ZI)V")?;
+
+        let write_result_class = env.find_class(NFS4_FILE_WRITE_RESULT_CLASS_NAME)?;
+        let write_result_ctor = env.get_method_id(&write_result_class, CTOR_NAME, "(I)V")?;
+
+        let array_list_class = env.find_class("java/util/ArrayList")?;
+        let array_list_ctor = env.get_method_id(&array_list_class, CTOR_NAME, "()V")?;
+        let array_list_add =
+            env.get_method_id(&array_list_class, "add", "(Ljava/lang/Object;)Z")?;
+
+        let hash_map_class = env.find_class("java/util/HashMap")?;
+        let hash_map_ctor = env.get_method_id(&hash_map_class, CTOR_NAME, "()V")?;
+        let hash_map_put = env.get_method_id(
+            &hash_map_class,
+            "put",
+            "(Ljava/lang/Object;Ljava/lang/Object;)Ljava/lang/Object;",
+        )?;
+
+        let long_class = env.find_class("java/lang/Long")?;
+        let long_value_of =
+            env.get_static_method_id(&long_class, "valueOf", "(J)Ljava/lang/Long;")?;
+        let integer_class = env.find_class("java/lang/Integer")?;
+        let integer_value_of =
+            env.get_static_method_id(&integer_class, "valueOf", "(I)Ljava/lang/Integer;")?;
+        let boolean_class = env.find_class("java/lang/Boolean")?;
+        let boolean_value_of =
+            env.get_static_method_id(&boolean_class, "valueOf", "(Z)Ljava/lang/Boolean;")?;
+
+        let posix_file_permissions_class =
+            env.find_class("java/nio/file/attribute/PosixFilePermissions")?;
+        let posix_file_permissions_from_string = env.get_static_method_id(
+            &posix_file_permissions_class,
+            "fromString",
+            "(Ljava/lang/String;)Ljava/util/Set;",
+        )?;
+
+        let buffer_class = env.find_class("java/nio/Buffer")?;
+        let buffer_position = env.get_method_id(&buffer_class, "position", "()I")?;
+        let buffer_set_position =
+            env.get_method_id(&buffer_class, "position", "(I)Ljava/nio/Buffer;")?;
+        let buffer_remaining = env.get_method_id(&buffer_class, "remaining", "()I")?;
+        let buffer_is_read_only = env.get_method_id(&buffer_class, "isReadOnly", "()Z")?;
+        let read_only_buffer_exception_class =
+            env.find_class("java/nio/ReadOnlyBufferException")?;
+        let read_only_buffer_exception_ctor =
+            env.get_method_id(&read_only_buffer_exception_class, CTOR_NAME, "()V")?;
+        let closed_by_interrupt_exception_class =
+            env.find_class("java/nio/channels/ClosedByInterruptException")?;
+        let closed_by_interrupt_exception_ctor =
+            env.get_method_id(&closed_by_interrupt_exception_class, CTOR_NAME, "()V")?;
+        let uri_syntax_exception_class = env.find_class("java/net/URISyntaxException")?;
+        let uri_syntax_exception_ctor = env.get_method_id(
+            &uri_syntax_exception_class,
+            CTOR_NAME,
+            "(Ljava/lang/String;Ljava/lang/String;)V",
+        )?;
+
+        let thread_class = env.find_class("java/lang/Thread")?;
+        let thread_current_thread =
+            env.get_static_method_id(&thread_class, "currentThread", "()Ljava/lang/Thread;")?;
+        let thread_is_interrupted = env.get_method_id(&thread_class, "isInterrupted", "()Z")?;
+
+        Ok(JniCache {
+            filetime_class: env.new_global_ref(filetime_class)?,
+            filetime_from_millis,
+            nfs4_file_attributes_class: env.new_global_ref(nfs4_file_attributes_class)?,
+            nfs4_file_attributes_ctor,
+            read_result_class: env.new_global_ref(read_result_class)?,
+            read_result_ctor,
+            write_result_class: env.new_global_ref(write_result_class)?,
+            write_result_ctor,
+            export_info: OptionalClass::new(
+                NFS4_EXPORT_INFO_CLASS_NAME,
+                "(Ljava/lang/String;JJLjava/util/List;)V",
+            ),
+            user_principal: OptionalClass::new(
+                NFS4_USER_PRINCIPAL_CLASS_NAME,
+                "(Ljava/lang/String;)V",
+            ),
+            group_principal: OptionalClass::new(
+                NFS4_GROUP_PRINCIPAL_CLASS_NAME,
+                "(Ljava/lang/String;)V",
+            ),
+            posix_file_permissions_class: env.new_global_ref(posix_file_permissions_class)?,
+            posix_file_permissions_from_string,
+            array_list_class: env.new_global_ref(array_list_class)?,
+            array_list_ctor,
+            array_list_add,
+            hash_map_class: env.new_global_ref(hash_map_class)?,
+            hash_map_ctor,
+            hash_map_put,
+            long_class: env.new_global_ref(long_class)?,
+            long_value_of,
+            integer_class: env.new_global_ref(integer_class)?,
+            integer_value_of,
+            boolean_class: env.new_global_ref(boolean_class)?,
+            boolean_value_of,
+            buffer_class: env.new_global_ref(buffer_class)?,
+            buffer_position,
+            buffer_set_position,
+            buffer_remaining,
+            buffer_is_read_only,
+            read_only_buffer_exception_class: env
+                .new_global_ref(read_only_buffer_exception_class)?,
+            read_only_buffer_exception_ctor,
+            closed_by_interrupt_exception_class: env
+                .new_global_ref(closed_by_interrupt_exception_class)?,
+            closed_by_interrupt_exception_ctor,
+            uri_syntax_exception_class: env.new_global_ref(uri_syntax_exception_class)?,
+            uri_syntax_exception_ctor,
+            thread_class: env.new_global_ref(thread_class)?,
+            thread_current_thread,
+            thread_is_interrupted,
+        })
+    }
+}
+
+/// Borrows a cached global class reference as a `JClass`.
+pub fn as_class(global: &GlobalRef) -> &JClass<'static> {
+    <&JClass>::from(global.as_obj())
+}
+
+/// Resolves the cache unless an earlier load of the library already did.
+pub fn init_jni_cache(env: &mut JNIEnv) -> Result<(), NfscrsJniError> {
+    if JNI_CACHE.get().is_none() {
+        let cache = JniCache::resolve(env)?;
+        let _ = JNI_CACHE.set(cache);
+    }
+    Ok(())
+}
+
+pub fn jni_cache() -> Result<&'static JniCache, NfscrsJniError> {
+    JNI_CACHE
+        .get()
+        .ok_or_else(|| NfscrsJniError::NFSCRSJNIError("JNI cache is not initialized".to_string()))
+}
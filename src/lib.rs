@@ -1,13 +1,12 @@
-use std::net::ToSocketAddrs;
-
-use nfscrs::fattr4::set_bitmap;
+use nfscrs::NFSClientSession;
+use nfscrs::fattr4::{FAttr4, FAttr4Type, fattr4_names, set_bitmap};
 use nfscrs::nfs4_types::{BitMap4, NFSFType4};
 use nfscrs::nfs4_utils::nfs4time_to_miliseconds;
+use nfscrs::nfscrs_error::NFSCRSError;
 use nfscrs::nfscrs_types::AbsolutePath;
-use nfscrs::{self, NFSClientBuilder, NFSClientSession};
 
-use jni::JNIEnv;
-use jni::objects::{JClass, JStaticMethodID, JValue};
+use jni::objects::{JClass, JIntArray, JObjectArray, JStaticMethodID, JValue};
+use jni::{JNIEnv, JavaVM};
 use jni::{
     objects::{JObject, JString},
     sys::{jint, jlong, jobject},
@@ -15,14 +14,42 @@ use jni::{
 
 use crate::attr_utils::{
     get_access_time, get_create_time, get_file_mode, get_file_size, get_filetype, get_modify_time,
+    named_attr_to_java, parse_attr_names,
 };
-use crate::error::{handle_error, throw_nfs_error};
-
+use crate::connect::{claim_socket, establish, establish_over_socket};
+use crate::credential::AuthSysCredential;
+use crate::error::{NfscrsJniError, handle_error};
+use crate::jni_utils::{as_class, init_jni_cache, jni_cache};
+use crate::nfs_url::NfsUrl;
+use crate::retry::{Retryable, call_interruptible};
+use crate::security::check_security;
+use crate::session::{SessionGuard, SessionHandle, session_handle};
+use crate::session_options::{SessionOptions, policy_from_java};
+
+mod async_ops;
 mod attr_utils;
+mod byte_buffer;
+mod chunked_io;
+mod connect;
+mod copy_ops;
+mod credential;
 mod error;
+mod exports;
 mod file_ops;
 mod file_utils;
+mod interrupt;
 mod jni_utils;
+mod nfs_url;
+mod opened_file;
+mod read_ahead;
+mod retry;
+mod security;
+mod session;
+mod session_options;
+mod sparse_ops;
+mod transfer;
+mod write_behind;
+mod xattr_ops;
 
 use android_logger;
 use log;
@@ -41,13 +68,38 @@ fn init_android_logger() {
 #[allow(non_snake_case)]
 #[unsafe(no_mangle)]
 pub extern "system" fn JNI_OnLoad(
-    _vm: *mut jni::sys::JavaVM,
+    vm: *mut jni::sys::JavaVM,
     _reserved: *mut std::ffi::c_void,
 ) -> jint {
     init_android_logger();
+    let vm = match unsafe { JavaVM::from_raw(vm) } {
+        Ok(vm) => vm,
+        Err(e) => {
+            tracing::error!("JNI_OnLoad: invalid JavaVM: {e}");
+            return jni::sys::JNI_ERR;
+        }
+    };
+    let mut env = match vm.get_env() {
+        Ok(env) => env,
+        Err(e) => {
+            tracing::error!("JNI_OnLoad: cannot get JNIEnv: {e}");
+            return jni::sys::JNI_ERR;
+        }
+    };
+    if let Err(e) = init_jni_cache(&mut env) {
+        tracing::error!("JNI_OnLoad: failed to cache JNI classes: {e}");
+        return jni::sys::JNI_ERR;
+    }
     jni::sys::JNI_VERSION_1_6
 }
 
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn JNI_OnUnload(_vm: *mut jni::sys::JavaVM, _reserved: *mut std::ffi::c_void) {
+    // The JNI cache is kept: natives may still be running on other threads,
+    // and its global references go away with the VM.
+}
+
 #[allow(non_snake_case)]
 #[unsafe(no_mangle)]
 pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_getClientSession(
@@ -59,176 +111,433 @@ pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_getClientSessi
     client_owner: JString, // TODO: pass client_owner from application.
 ) -> jlong {
     tracing::debug!("getClientSession!");
-    let r_addr_result = match env.get_string(&remote_addr) {
-        Ok(s) => s,
+    let credential = AuthSysCredential::new(uid as u32, gid as u32);
+    let result = positional_options(&mut env, credential, &remote_addr, &client_owner)
+        .and_then(|options| create_positional_session(&options));
+    match result {
+        Ok(r) => r,
         Err(e) => {
-            let _ = env.throw_new("java/io/IOException", format!("Invalid remote_addr: {e}"));
+            handle_error(&mut env, &e);
             return 0;
         }
-    };
+    }
+}
 
-    let r_addr = match r_addr_result.to_str() {
-        Ok(s) => s,
+/// Like `getClientSession`, with AUTH_SYS supplementary `gids` and
+/// `machineName`. Either may be null.
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_getClientSessionWithCredential(
+    mut env: JNIEnv,
+    _this: JObject,
+    uid: jint,
+    gid: jint,
+    gids: JIntArray,
+    machine_name: JString,
+    remote_addr: JString,
+    client_owner: JString,
+) -> jlong {
+    tracing::debug!("getClientSessionWithCredential!");
+    let result = AuthSysCredential::from_java(&mut env, uid, gid, &gids, &machine_name)
+        .and_then(|credential| {
+            positional_options(&mut env, credential, &remote_addr, &client_owner)
+        })
+        .and_then(|options| create_positional_session(&options));
+    match result {
+        Ok(r) => r,
         Err(e) => {
-            let _ = env.throw_new("java/io/IOException", format!("UTF-8 decode error: {e}"));
+            handle_error(&mut env, &e);
             return 0;
         }
-    };
+    }
+}
 
-    let parsed_addr = match r_addr.to_socket_addrs().and_then(|mut it| {
-        it.next().ok_or(std::io::Error::new(
-            std::io::ErrorKind::InvalidInput,
-            "could not resolve to any address", // 自定义错误信息
-        ))
-    }) {
-        Ok(addr) => addr,
+/// Establishes a session described by an `NFS4SessionOptions`. Invalid
+/// options are reported as `IllegalArgumentException`.
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_getClientSessionWithOptions(
+    mut env: JNIEnv,
+    _this: JObject,
+    options: JObject,
+) -> jlong {
+    tracing::debug!("getClientSessionWithOptions!");
+    let result =
+        SessionOptions::from_java(&mut env, &options).and_then(|options| create_session(&options));
+    match result {
+        Ok(r) => r,
         Err(e) => {
-            let _ = env.throw_new(
-                "java/net/URISyntaxException",
-                format!("Invalid address: {e}"),
-            );
+            handle_error(&mut env, &e);
             return 0;
         }
-    };
+    }
+}
+
+/// Reads the positional string arguments. As before session options
+/// existed, an unreadable string is reported as `IOException`.
+fn positional_options(
+    env: &mut JNIEnv,
+    credential: AuthSysCredential,
+    remote_addr: &JString,
+    client_owner: &JString,
+) -> Result<SessionOptions, NfscrsJniError> {
+    let r_addr = get_io_string(env, remote_addr, "remote_addr")?;
+    let client_owner_str = get_io_string(env, client_owner, "client_owner")?;
+    Ok(SessionOptions::new(credential, r_addr, client_owner_str))
+}
+
+fn get_io_string(env: &mut JNIEnv, s: &JString, name: &str) -> Result<String, NfscrsJniError> {
+    match env.get_string(s) {
+        Ok(s) => Ok(s.into()),
+        Err(e) => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Invalid {name}: {e}"),
+        )
+        .into()),
+    }
+}
+
+/// `create_session` for the positional entry points, which report an
+/// address that does not resolve as `URISyntaxException`.
+fn create_positional_session(options: &SessionOptions) -> Result<jlong, NfscrsJniError> {
+    create_session(options).map_err(|e| match e {
+        NfscrsJniError::UnknownHost(reason) => NfscrsJniError::URISyntax(
+            options.server_address.clone(),
+            format!("Invalid address: {reason}"),
+        ),
+        e => e,
+    })
+}
 
-    let client_owner_string = match env.get_string(&client_owner) {
-        Ok(s) => s,
+/// Like `getClientSessionWithCredential`, over an already connected TCP
+/// socket created on the Java side (e.g. bound to a specific `Network` or
+/// from a VPN), or a stream socket without a peer address such as one end
+/// of a socketpair to a proxy. Ownership of `fd` passes to the session, as with
+/// `ParcelFileDescriptor.detachFd()`; it is closed if the call fails.
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_getClientSessionOverFd(
+    mut env: JNIEnv,
+    _this: JObject,
+    fd: jint,
+    uid: jint,
+    gid: jint,
+    gids: JIntArray,
+    machine_name: JString,
+    client_owner: JString,
+) -> jlong {
+    tracing::debug!("getClientSessionOverFd!");
+    let stream = match unsafe { claim_socket(fd) } {
+        Ok(stream) => stream,
         Err(e) => {
-            let _ = env.throw_new("java/io/IOException", format!("Invalid remote_addr: {e}"));
+            handle_error(&mut env, &e);
             return 0;
         }
     };
-    let client_owner_str = match client_owner_string.to_str() {
-        Ok(s) => s,
+    let result = AuthSysCredential::from_java(&mut env, uid, gid, &gids, &machine_name)
+        .and_then(|credential| {
+            let client_owner_str: String = env.get_string(&client_owner)?.into();
+            Ok(SessionOptions::new(
+                credential,
+                String::new(),
+                client_owner_str,
+            ))
+        })
+        .and_then(|options| {
+            let session = establish_over_socket(&options, stream)?;
+            register_session(session, &options, false)
+        });
+    match result {
+        Ok(r) => r,
         Err(e) => {
-            let _ = env.throw_new("java/io/IOException", format!("UTF-8 decode error: {e}"));
+            handle_error(&mut env, &e);
             return 0;
         }
-    };
+    }
+}
 
-    let session = match NFSClientBuilder::new(
-        uid as u32,
-        gid as u32,
-        parsed_addr,
-        client_owner_str.as_bytes().to_owned(),
+fn create_session(options: &SessionOptions) -> Result<jlong, NfscrsJniError> {
+    let session = establish(options)?;
+    register_session(session, options, true)
+}
+
+/// Checks the security flavor of the session root and wraps the session
+/// in a handle for Java. `reconnectable` when `options` can connect again,
+/// for parallel transfers.
+fn register_session(
+    mut session: NFSClientSession,
+    options: &SessionOptions,
+    reconnectable: bool,
+) -> Result<jlong, NfscrsJniError> {
+    let root =
+        AbsolutePath::try_from(options.root.clone()).map_err(|e| NFSCRSError::InnerError(e))?;
+    check_security(&mut session, &root)?;
+    check_root(&mut session, &root)?;
+    let credential = options.credential.clone();
+    Ok(SessionHandle::new(
+        session,
+        credential,
+        options.root.clone(),
+        options.policy,
+        options.io_size_limits,
+        reconnectable.then(|| options.clone()),
     )
-    .establish_session()
+    .into_jlong())
+}
+
+/// Fails session creation when the root is missing or not a directory,
+/// rather than every later call. Only the path is kept afterwards: nfscrs
+/// has no operations relative to a filehandle, so the root cannot be
+/// pinned as one and each RPC walks it from the pseudo-root again.
+fn check_root(session: &mut NFSClientSession, root: &AbsolutePath) -> Result<(), NfscrsJniError> {
+    let fattr4 = session.get_attr(root, basic_attr_bitmap())?;
+    match fattr4.fetch_attr(fattr4_names::FATTR4_TYPE) {
+        Ok(FAttr4Type::FATTR4_TYPE(NFSFType4::NF4DIR)) => Ok(()),
+        _ => Err(NfscrsJniError::FileSystemError(format!(
+            "{root:?}: session root is not a directory"
+        ))),
+    }
+}
+
+/// Establishes a session from an `nfs://host[:port]/export?options` URL
+/// (see `NfsUrl`). The session is rooted at the export: Java paths are
+/// relative to it. `uid`, `gid` and `clientOwner` are used unless the URL
+/// overrides the ids in its query.
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_getClientSessionFromUrl(
+    mut env: JNIEnv,
+    _this: JObject,
+    url: JString,
+    uid: jint,
+    gid: jint,
+    client_owner: JString,
+) -> jlong {
+    tracing::debug!("getClientSessionFromUrl!");
+    match url_options(&mut env, &url, uid, gid, &client_owner)
+        .and_then(|options| create_session(&options))
     {
-        Ok(session) => session,
+        Ok(r) => r,
         Err(e) => {
-            throw_nfs_error(&mut env, &e);
+            handle_error(&mut env, &e);
             return 0;
         }
-    };
+    }
+}
 
-    let addr = Box::into_raw(Box::new(session));
-    addr as jlong
+fn url_options(
+    env: &mut JNIEnv,
+    url: &JString,
+    uid: jint,
+    gid: jint,
+    client_owner: &JString,
+) -> Result<SessionOptions, NfscrsJniError> {
+    let url: String = env.get_string(url)?.into();
+    let nfs_url = NfsUrl::parse(&url)?;
+    tracing::debug!("nfs url: {:?}", nfs_url);
+    let client_owner_str: String = env.get_string(client_owner)?.into();
+    let credential = AuthSysCredential::new(uid as u32, gid as u32);
+    let mut options = SessionOptions::new(credential, nfs_url.server_address(), client_owner_str);
+    options.root = nfs_url.path.clone();
+    nfs_url.apply_query(&mut options)?;
+    Ok(options)
 }
 
+/// Replaces the AUTH_SYS credential of a live session. Takes effect for the
+/// next RPC; operations already running finish with the old credential.
+/// Credential views keep their own credential.
 #[allow(non_snake_case)]
 #[unsafe(no_mangle)]
-pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_listDir(
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_setCredential(
     mut env: JNIEnv,
     _this: JObject,
     session: jlong,
-    path: JString,
-) -> jobject {
-    let path_result = match env.get_string(&path) {
-        Ok(p) => p,
-        Err(e) => {
-            let _ = env.throw_new("java/io/IOException", format!("Invalid path: {e}"));
-            return std::ptr::null_mut();
-        }
-    };
-    let path: &str = match path_result.to_str() {
-        Ok(p) => p,
+    uid: jint,
+    gid: jint,
+    gids: JIntArray,
+    machine_name: JString,
+) {
+    let session_handle = unsafe { session_handle(session) };
+    let credential = AuthSysCredential::from_java(&mut env, uid, gid, &gids, &machine_name);
+    tracing::debug!("set_credential: {:?}", credential);
+    match credential.and_then(|c| session_handle.set_credential(c)) {
+        Ok(_) => {}
         Err(e) => {
-            let _ = env.throw_new("java/io/IOException", format!("utf8 decode error: {e}"));
-            return std::ptr::null_mut();
+            handle_error(&mut env, &e);
         }
-    };
+    }
+}
 
-    let abs_path = match AbsolutePath::try_from(path) {
-        Ok(p) => p,
+/// Replaces the deadline and retry policy of a live session, including its
+/// credential views: `timeoutMillis` bounds each call and each RPC (0 for
+/// none), operations are retried up to `maxRetries` times after
+/// NFS4ERR_DELAY or NFS4ERR_GRACE, and reads also after transport errors,
+/// starting `backoffMillis` apart (0 for the default). Calls already running keep the old policy. See
+/// `createPolicyView` for a policy of a single call.
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_setOperationPolicy(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    timeout_millis: jlong,
+    max_retries: jint,
+    backoff_millis: jlong,
+) {
+    let session_handle = unsafe { session_handle(session) };
+    let policy = policy_from_java(timeout_millis, max_retries, backoff_millis);
+    tracing::debug!("set_operation_policy: {:?}", policy);
+    match policy {
+        Ok(policy) => session_handle.set_policy(policy),
         Err(e) => {
-            let _ = env.throw_new("java/io/IOException", format!("not absolute path: {e}"));
-            return std::ptr::null_mut();
+            handle_error(&mut env, &e);
         }
-    };
+    }
+}
 
-    let session_ptr = session as *mut NFSClientSession;
-    let session_ref: &mut NFSClientSession = unsafe { &mut *session_ptr };
-    let r = match session_ref.list_dir(&abs_path) {
-        Ok(r) => r,
+/// Returns a session handle that shares the NFS session of `session` but
+/// sends the given AUTH_SYS credential. It can be passed wherever a session
+/// handle is expected, so each user of a multi-user app is authorized as
+/// themselves over one connection. `gids` may be null; a null
+/// `machineName` keeps the one of `session`. Views need not be released;
+/// see `createPolicyView` for how many a session can have.
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_createCredentialView(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    uid: jint,
+    gid: jint,
+    gids: JIntArray,
+    machine_name: JString,
+) -> jlong {
+    let session_handle = unsafe { session_handle(session) };
+    match AuthSysCredential::from_java(&mut env, uid, gid, &gids, &machine_name) {
+        Ok(credential) => {
+            tracing::debug!("create_credential_view: {:?}", credential);
+            match session_handle.view(credential) {
+                Ok(view) => view as *const SessionHandle as jlong,
+                Err(e) => {
+                    handle_error(&mut env, &e);
+                    0
+                }
+            }
+        }
         Err(e) => {
-            let _ = env.throw_new("java/io/IOException", format!("list dir error: {e}"));
-            return std::ptr::null_mut();
+            handle_error(&mut env, &e);
+            return 0;
         }
-    };
+    }
+}
 
-    let rr = r.iter().map(|e| String::from_utf8_lossy(&e.name));
-    let array_list_class = match env.find_class("java/util/ArrayList") {
-        Ok(class) => class,
-        Err(e) => {
-            let _ = env.throw_new(
-                "java/io/IOException",
-                format!("ArrayList class not found: {e}"),
-            );
-            return std::ptr::null_mut();
+/// Returns a session handle that shares the NFS session and credential of
+/// `session` but whose calls follow their own deadline and retry policy,
+/// with the arguments of `setOperationPolicy`. Passing it for one call gives
+/// that call its own timeout; later `setOperationPolicy` calls do not
+/// change it. Views need not be released, but are kept until the process
+/// exits: a session has at most `MAX_VIEWS` (256) distinct views, after
+/// which creating another throws `IllegalArgumentException`.
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_createPolicyView(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    timeout_millis: jlong,
+    max_retries: jint,
+    backoff_millis: jlong,
+) -> jlong {
+    let session_handle = unsafe { session_handle(session) };
+    match policy_from_java(timeout_millis, max_retries, backoff_millis) {
+        Ok(policy) => {
+            tracing::debug!("create_policy_view: {:?}", policy);
+            match session_handle.policy_view(policy) {
+                Ok(view) => view as *const SessionHandle as jlong,
+                Err(e) => {
+                    handle_error(&mut env, &e);
+                    0
+                }
+            }
         }
-    };
-    let array_list_obj = match env.new_object(&array_list_class, "()V", &[]) {
-        Ok(obj) => obj,
         Err(e) => {
-            let _ = env.throw_new(
-                "java/io/IOException",
-                format!("Failed to create ArrayList: {e}"),
-            );
-            return std::ptr::null_mut();
+            handle_error(&mut env, &e);
+            return 0;
         }
-    };
-    let add_method = match env.get_method_id(&array_list_class, "add", "(Ljava/lang/Object;)Z") {
-        Ok(method) => method,
+    }
+}
+
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_listDir(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    path: JString,
+) -> jobject {
+    let session_handle = unsafe { session_handle(session) };
+    match list_dir(session_handle, &mut env, &path) {
+        Ok(r) => r,
         Err(e) => {
-            let _ = env.throw_new(
-                "java/io/IOException",
-                format!("Failed to get add() method: {e}"),
-            );
+            handle_error(&mut env, &e);
             return std::ptr::null_mut();
         }
+    }
+}
+
+/// Interruptible, and bounded by the session's timeout.
+pub fn list_dir(
+    session_handle: &'static SessionHandle,
+    env: &mut JNIEnv,
+    path: &JString,
+) -> Result<jobject, NfscrsJniError> {
+    let path_str = get_io_string(env, path, "path")?;
+    let names = call_interruptible(env, session_handle, "listDir", move |session_ref| {
+        list_dir_names(session_ref, path_str.clone())
+    })?;
+    names_to_java(env, names)
+}
+
+fn list_dir_names(
+    session_ref: &mut SessionGuard,
+    path: String,
+) -> Result<Vec<String>, NfscrsJniError> {
+    let abs_path = session_ref.resolve(path)?;
+    let r = match session_ref.list_dir(&abs_path) {
+        Ok(r) => r,
+        Err(e) if e.is_retryable() => return Err(e.into()),
+        // READDIR failures have always been a plain IOException here.
+        Err(e) => return Err(std::io::Error::other(format!("list dir error: {e}")).into()),
     };
+    Ok(r.iter()
+        .map(|e| String::from_utf8_lossy(&e.name).into_owned())
+        .collect())
+}
 
-    for name in rr {
-        let jname: JString = match env.new_string(name) {
-            Ok(jstring) => jstring,
-            Err(e) => {
-                let _ = env.throw_new(
-                    "java/io/IOException",
-                    format!("Failed to create JString: {e}"),
-                );
-                return std::ptr::null_mut();
-            }
-        };
+fn names_to_java(env: &mut JNIEnv, names: Vec<String>) -> Result<jobject, NfscrsJniError> {
+    let cache = jni_cache()?;
+    let array_list_obj = unsafe {
+        env.new_object_unchecked(
+            as_class(&cache.array_list_class),
+            cache.array_list_ctor,
+            &[],
+        )
+    }?;
+
+    for name in names {
+        let jname: JString = env.new_string(name)?;
         let jval = JValue::Object(&jname).as_jni();
         unsafe {
-            match env.call_method_unchecked(
+            env.call_method_unchecked(
                 &array_list_obj,
-                add_method,
+                cache.array_list_add,
                 jni::signature::ReturnType::Primitive(jni::signature::Primitive::Boolean),
                 &[jval],
-            ) {
-                Ok(_) => {}
-                Err(e) => {
-                    let _ = env.throw_new(
-                        "java/io/IOException",
-                        format!("Failed to call add() method: {e}"),
-                    );
-                    return std::ptr::null_mut();
-                }
-            };
-        }
+            )
+        }?;
+        env.delete_local_ref(jname)?;
     }
-    array_list_obj.into_raw()
+    Ok(array_list_obj.into_raw())
 }
 
 #[allow(non_snake_case)]
@@ -239,134 +548,151 @@ pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_readAttr(
     session: jlong,
     path: JString,
 ) -> jobject {
-    let path_str: String = match env.get_string(&path) {
-        Ok(s) => s.into(),
-        Err(e) => {
-            let _ = env.throw_new("java/io/IOException", format!("Invalid path: {e}"));
-            return std::ptr::null_mut();
-        }
-    };
-
-    let abs_path = match AbsolutePath::try_from(path_str) {
-        Ok(p) => p,
-        Err(e) => {
-            let _ = env.throw_new("java/io/IOException", format!("not absolute path: {e}"));
-            return std::ptr::null_mut();
-        }
-    };
-
-    let session_ptr = session as *mut NFSClientSession;
-    let session_ref: &mut NFSClientSession = unsafe { &mut *session_ptr };
-
-    let fattr4 = match session_ref.get_attr(&abs_path, basic_attr_bitmap()) {
-        Ok(s) => s,
-        Err(e) => {
-            crate::error::throw_nfs_error(&mut env, &e);
-            return std::ptr::null_mut();
-        }
-    };
-
-    let filetype = get_filetype(&fattr4, &mut env);
-
-    let filesize = match get_file_size(&fattr4) {
-        Ok(size) => size,
+    let session_handle = unsafe { session_handle(session) };
+    match read_attr(session_handle, &mut env, &path) {
+        Ok(r) => r,
         Err(e) => {
             handle_error(&mut env, &e);
             return std::ptr::null_mut();
         }
-    };
+    }
+}
+
+/// Interruptible, and bounded by the session's timeout.
+pub fn read_attr(
+    session_handle: &'static SessionHandle,
+    env: &mut JNIEnv,
+    path: &JString,
+) -> Result<jobject, NfscrsJniError> {
+    let path_str = get_io_string(env, path, "path")?;
+    let fattr4 = call_interruptible(env, session_handle, "readAttr", move |session_ref| {
+        let abs_path = session_ref.resolve(path_str.clone())?;
+        Ok(session_ref.get_attr(&abs_path, basic_attr_bitmap())?)
+    })?;
+    attrs_to_java(env, &fattr4)
+}
 
-    let filemode = get_file_mode(&fattr4, &mut env) as i32;
-    let access_time = get_access_time(&fattr4, &mut env);
+fn attrs_to_java(env: &mut JNIEnv, fattr4: &FAttr4) -> Result<jobject, NfscrsJniError> {
+    let filetype = get_filetype(fattr4, env);
+    let filesize = get_file_size(fattr4)?;
+    let filemode = get_file_mode(fattr4, env) as i32;
+    let access_time = get_access_time(fattr4, env);
     let access_time_millis: jlong = nfs4time_to_miliseconds(&access_time);
 
-    let modify_time = get_modify_time(&fattr4, &mut env);
+    let modify_time = get_modify_time(fattr4, env);
     let modify_time_millis: jlong = nfs4time_to_miliseconds(&modify_time);
 
-    let create_time = get_create_time(&fattr4, &mut env);
+    let create_time = get_create_time(fattr4, env);
     let create_time_millis: jlong = nfs4time_to_miliseconds(&create_time);
 
-    let filetime_class = env
-        .find_class("java/nio/file/attribute/FileTime")
-        .expect("FileTime class not found");
-    let from_millis = env
-        .get_static_method_id(
-            &filetime_class,
-            "fromMillis",
-            "(J)Ljava/nio/file/attribute/FileTime;",
-        )
-        .expect("FileTime.fromMillis not found");
-
-    let last_access_time =
-        match create_filetime(&filetime_class, from_millis, access_time_millis, &mut env) {
-            Ok(time) => time,
-            Err(e) => {
-                let _ = env.throw_new(
-                    "java/io/IOException",
-                    format!("Failed to create FileTime: {}", e),
-                );
-                return std::ptr::null_mut();
-            }
-        };
-    let last_modify_time =
-        match create_filetime(&filetime_class, from_millis, modify_time_millis, &mut env) {
-            Ok(time) => time,
-            Err(e) => {
-                let _ = env.throw_new(
-                    "java/io/IOException",
-                    format!("Failed to create FileTime: {}", e),
-                );
-                return std::ptr::null_mut();
-            }
-        };
-
-    let creation_time =
-        match create_filetime(&filetime_class, from_millis, create_time_millis, &mut env) {
-            Ok(time) => time,
-            Err(e) => {
-                let _ = env.throw_new(
-                    "java/io/IOException",
-                    format!("Failed to create FileTime: {}", e),
-                );
-                return std::ptr::null_mut();
-            }
-        };
-
-    let nfs_attrs_class = match env.find_class("com/algebnaly/nfs4c/NFS4FileAttributes") {
-        Ok(class) => class,
-        Err(e) => {
-            let _ = env.throw_new("java/io/IOException", format!("class not found: {e}"));
-            return std::ptr::null_mut();
-        }
-    };
-
-    let ctor_sig = "(Ljava/nio/file/attribute/FileTime;Ljava/nio/file/attribute/FileTime;Ljava/nio/file/attribute/FileTime;ZZZZJILjava/lang/Object;)V";
+    let cache = jni_cache()?;
+    let filetime_class = as_class(&cache.filetime_class);
+    let from_millis = cache.filetime_from_millis;
+    let last_access_time = create_filetime(filetime_class, from_millis, access_time_millis, env)?;
+    let last_modify_time = create_filetime(filetime_class, from_millis, modify_time_millis, env)?;
+    let creation_time = create_filetime(filetime_class, from_millis, create_time_millis, env)?;
 
     let is_regular = matches!(filetype, NFSFType4::NF4REG);
     let is_directory = matches!(filetype, NFSFType4::NF4DIR);
     let is_symlink = matches!(filetype, NFSFType4::NF4LNK);
     let is_other = !is_regular && !is_directory && !is_symlink;
 
-    let obj = env
-        .new_object(
-            nfs_attrs_class,
-            ctor_sig,
+    let obj = unsafe {
+        env.new_object_unchecked(
+            as_class(&cache.nfs4_file_attributes_class),
+            cache.nfs4_file_attributes_ctor,
             &[
-                JValue::Object(&last_access_time),
-                JValue::Object(&last_modify_time),
-                JValue::Object(&creation_time),
-                JValue::Bool(if is_regular { 1 } else { 0 }),
-                JValue::Bool(if is_directory { 1 } else { 0 }),
-                JValue::Bool(if is_symlink { 1 } else { 0 }),
-                JValue::Bool(if is_other { 1 } else { 0 }),
-                JValue::Long(filesize as jlong),
-                JValue::Int(filemode),
-                JValue::Object(&JObject::null()),
+                JValue::Object(&last_access_time).as_jni(),
+                JValue::Object(&last_modify_time).as_jni(),
+                JValue::Object(&creation_time).as_jni(),
+                JValue::Bool(if is_regular { 1 } else { 0 }).as_jni(),
+                JValue::Bool(if is_directory { 1 } else { 0 }).as_jni(),
+                JValue::Bool(if is_symlink { 1 } else { 0 }).as_jni(),
+                JValue::Bool(if is_other { 1 } else { 0 }).as_jni(),
+                JValue::Long(filesize as jlong).as_jni(),
+                JValue::Int(filemode).as_jni(),
+                JValue::Object(&JObject::null()).as_jni(),
             ],
         )
-        .expect("Failed to create NFS4FileAttributes");
+    }?;
+
+    Ok(obj.into_raw())
+}
+
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_readAttrs(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    path: JString,
+    names: JObjectArray,
+) -> jobject {
+    let session_handle = unsafe { session_handle(session) };
+    match read_attrs(session_handle, &mut env, &path, &names) {
+        Ok(r) => r,
+        Err(e) => {
+            handle_error(&mut env, &e);
+            return std::ptr::null_mut();
+        }
+    }
+}
+
+fn read_attrs(
+    session_handle: &'static SessionHandle,
+    env: &mut JNIEnv,
+    path: &JString,
+    names: &JObjectArray, // String[]
+) -> Result<jobject, NfscrsJniError> {
+    let path_str = get_io_string(env, path, "path")?;
+
+    let names_len = env.get_array_length(names)?;
+    let mut name_strings = Vec::with_capacity(names_len as usize);
+    for i in 0..names_len {
+        let name_obj = JString::from(env.get_object_array_element(names, i)?);
+        let name = get_io_string(env, &name_obj, "attribute name")?;
+        env.delete_local_ref(name_obj)?;
+        name_strings.push(name);
+    }
+    let attrs = parse_attr_names(&name_strings)?;
+
+    let mut bitmap = BitMap4::new();
+    for (_, attr) in &attrs {
+        attr.set_bits(&mut bitmap);
+    }
+
+    tracing::debug!("read_attrs: {:?} {:?}", path_str, name_strings);
+    let fattr4 = call_interruptible(env, session_handle, "readAttrs", move |session_ref| {
+        let abs_path = session_ref.resolve(path_str.clone())?;
+        Ok(session_ref.get_attr(&abs_path, bitmap.clone())?)
+    })?;
+
+    let cache = jni_cache()?;
+    let map = unsafe {
+        env.new_object_unchecked(as_class(&cache.hash_map_class), cache.hash_map_ctor, &[])
+    }?;
+    for (key, attr) in attrs {
+        let Some(value) = named_attr_to_java(&fattr4, attr, env)? else {
+            continue;
+        };
+        let jkey = env.new_string(key)?;
+        unsafe {
+            env.call_method_unchecked(
+                &map,
+                cache.hash_map_put,
+                jni::signature::ReturnType::Object,
+                &[
+                    JValue::Object(&jkey).as_jni(),
+                    JValue::Object(&value).as_jni(),
+                ],
+            )
+        }?;
+        env.delete_local_ref(jkey)?;
+        env.delete_local_ref(value)?;
+    }
 
-    obj.into_raw()
+    tracing::debug!("read_attrs ok : {:?}", name_strings);
+    Ok(map.into_raw())
 }
 
 fn basic_attr_bitmap() -> BitMap4 {
@@ -0,0 +1,275 @@
+use std::time::Duration;
+
+use crate::error::NfscrsJniError;
+use crate::session::join_under_root;
+use crate::session_options::SessionOptions;
+
+pub const DEFAULT_NFS_PORT: u16 = 2049;
+
+/// An RFC 2224-style `nfs://host[:port][/path][?key=value&...]` URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NfsUrl {
+    /// Host name or IP literal, without the brackets of an IPv6 literal.
+    pub host: String,
+    pub port: u16,
+    /// Normalized, percent-decoded export path; `/` when the URL has none.
+    pub path: String,
+    pub query: Vec<(String, String)>,
+}
+
+impl NfsUrl {
+    pub fn parse(url: &str) -> Result<NfsUrl, NfscrsJniError> {
+        let invalid = |reason: &str| NfscrsJniError::IllegalArgument(format!("{reason}: {url}"));
+
+        let rest = match url.split_once("://") {
+            Some((scheme, rest)) if scheme.eq_ignore_ascii_case("nfs") => rest,
+            _ => return Err(invalid("not an nfs:// URL")),
+        };
+        let (rest, query) = match rest.split_once('?') {
+            Some((rest, query)) => (rest, Some(query)),
+            None => (rest, None),
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(i) => rest.split_at(i),
+            None => (rest, "/"),
+        };
+        if authority.contains('@') {
+            return Err(invalid("user info is not supported in NFS URLs"));
+        }
+
+        let (host, port) = if let Some(bracketed) = authority.strip_prefix('[') {
+            let (host, after) = bracketed
+                .split_once(']')
+                .ok_or_else(|| invalid("unterminated IPv6 literal"))?;
+            let port = match after {
+                "" => None,
+                _ => Some(
+                    after
+                        .strip_prefix(':')
+                        .ok_or_else(|| invalid("unexpected text after IPv6 literal"))?,
+                ),
+            };
+            (
+                percent_decode(host).map_err(|_| invalid("bad IPv6 zone"))?,
+                port,
+            )
+        } else {
+            match authority.rsplit_once(':') {
+                Some((host, _)) if host.contains(':') => {
+                    return Err(invalid("IPv6 literals must be in brackets"));
+                }
+                Some((host, port)) => (host.to_string(), Some(port)),
+                None => (authority.to_string(), None),
+            }
+        };
+        if host.is_empty() {
+            return Err(invalid("missing host"));
+        }
+        let port = match port {
+            None | Some("") => DEFAULT_NFS_PORT,
+            Some(port) => port.parse().map_err(|_| invalid("invalid port"))?,
+        };
+
+        // Decoded per component, so that an encoded `/` cannot act as a
+        // separator.
+        let mut components = Vec::new();
+        for component in path.split('/') {
+            let component =
+                percent_decode(component).map_err(|_| invalid("invalid percent-encoding"))?;
+            if component.contains('/') {
+                return Err(invalid("encoded '/' in a path component"));
+            }
+            components.push(component);
+        }
+        let path = join_under_root("/", &components.join("/"))?;
+
+        let mut pairs = Vec::new();
+        for pair in query.unwrap_or("").split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let key = percent_decode(key).map_err(|_| invalid("invalid percent-encoding"))?;
+            let value = percent_decode(value).map_err(|_| invalid("invalid percent-encoding"))?;
+            pairs.push((key, value));
+        }
+
+        Ok(NfsUrl {
+            host,
+            port,
+            path,
+            query: pairs,
+        })
+    }
+
+    /// `host:port` for `ToSocketAddrs`, bracketing IPv6 literals.
+    pub fn server_address(&self) -> String {
+        if self.host.contains(':') {
+            format!("[{}]:{}", self.host, self.port)
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+
+    /// Applies the query options to `options`:
+    ///
+    /// - `uid`, `gid`: AUTH_SYS ids
+    /// - `gids`: comma-separated supplementary gids
+    /// - `machine`: AUTH_SYS machine name
+    /// - `version`: `4.1` or `4.2`
+    /// - `timeout`: connect timeout in milliseconds
+    pub fn apply_query(&self, options: &mut SessionOptions) -> Result<(), NfscrsJniError> {
+        for (key, value) in &self.query {
+            let invalid =
+                || NfscrsJniError::IllegalArgument(format!("invalid NFS URL option {key}={value}"));
+            match key.as_str() {
+                "uid" => options.credential.uid = value.parse().map_err(|_| invalid())?,
+                "gid" => options.credential.gid = value.parse().map_err(|_| invalid())?,
+                "gids" => {
+                    options.credential.gids = value
+                        .split(',')
+                        .filter(|g| !g.is_empty())
+                        .map(|g| g.parse().map_err(|_| invalid()))
+                        .collect::<Result<_, _>>()?;
+                }
+                "machine" => options.credential.machine_name = Some(value.clone()),
+                "version" => {
+                    options.minor_version = match value.as_str() {
+                        "4.1" => Some(1),
+                        "4.2" => Some(2),
+                        _ => return Err(invalid()),
+                    };
+                }
+                "timeout" => {
+                    let millis: u64 = value.parse().map_err(|_| invalid())?;
+                    options.connect_timeout = (millis > 0).then(|| Duration::from_millis(millis));
+                }
+                _ => {
+                    return Err(NfscrsJniError::IllegalArgument(format!(
+                        "unknown NFS URL option: {key}"
+                    )));
+                }
+            }
+        }
+        options.credential.validate()
+    }
+}
+
+fn percent_decode(s: &str) -> Result<String, ()> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            // Checked by hand: `from_str_radix` would accept a sign.
+            let hex = bytes.get(i + 1..i + 3).ok_or(())?;
+            if !hex.iter().all(u8::is_ascii_hexdigit) {
+                return Err(());
+            }
+            let hex = std::str::from_utf8(hex).map_err(|_| ())?;
+            out.push(u8::from_str_radix(hex, 16).map_err(|_| ())?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).map_err(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_host_port_path_and_query() {
+        let url = NfsUrl::parse("nfs://nas.local:2050/export/photos?uid=1000&gid=1000&version=4.1")
+            .unwrap();
+        assert_eq!(url.host, "nas.local");
+        assert_eq!(url.port, 2050);
+        assert_eq!(url.path, "/export/photos");
+        assert_eq!(
+            url.query,
+            vec![
+                ("uid".to_string(), "1000".to_string()),
+                ("gid".to_string(), "1000".to_string()),
+                ("version".to_string(), "4.1".to_string()),
+            ]
+        );
+        assert_eq!(url.server_address(), "nas.local:2050");
+    }
+
+    #[test]
+    fn defaults_port_and_path() {
+        let url = NfsUrl::parse("NFS://nas.local").unwrap();
+        assert_eq!(url.port, DEFAULT_NFS_PORT);
+        assert_eq!(url.path, "/");
+        assert_eq!(
+            NfsUrl::parse("nfs://nas.local:/x").unwrap().port,
+            DEFAULT_NFS_PORT
+        );
+    }
+
+    #[test]
+    fn rejects_bad_ports() {
+        assert!(NfsUrl::parse("nfs://nas.local:99999/").is_err());
+        assert!(NfsUrl::parse("nfs://nas.local:port/").is_err());
+    }
+
+    #[test]
+    fn parses_ipv6_literals() {
+        let url = NfsUrl::parse("nfs://[fe80::1%25eth0]:2050/export").unwrap();
+        assert_eq!(url.host, "fe80::1%eth0");
+        assert_eq!(url.port, 2050);
+        assert_eq!(url.server_address(), "[fe80::1%eth0]:2050");
+
+        let url = NfsUrl::parse("nfs://[::1]/").unwrap();
+        assert_eq!(url.host, "::1");
+        assert_eq!(url.port, DEFAULT_NFS_PORT);
+
+        assert!(NfsUrl::parse("nfs://fe80::1/export").is_err());
+        assert!(NfsUrl::parse("nfs://[::1/export").is_err());
+        assert!(NfsUrl::parse("nfs://[::1]2049/export").is_err());
+    }
+
+    #[test]
+    fn normalizes_the_path() {
+        assert_eq!(
+            NfsUrl::parse("nfs://h//export///a/").unwrap().path,
+            "/export/a"
+        );
+        assert_eq!(
+            NfsUrl::parse("nfs://h/export/./a/../b").unwrap().path,
+            "/export/b"
+        );
+        assert!(NfsUrl::parse("nfs://h/../etc").is_err());
+        assert!(NfsUrl::parse("nfs://h/export/%2e%2e/%2e%2e").is_err());
+    }
+
+    #[test]
+    fn decodes_percent_encoding() {
+        let url = NfsUrl::parse("nfs://h/my%20photos?machine=my%26box").unwrap();
+        assert_eq!(url.path, "/my photos");
+        assert_eq!(
+            url.query,
+            vec![("machine".to_string(), "my&box".to_string())]
+        );
+        assert!(NfsUrl::parse("nfs://h/bad%zz").is_err());
+        assert!(NfsUrl::parse("nfs://h/short%2").is_err());
+        assert!(NfsUrl::parse("nfs://h/sign%+1").is_err());
+        assert!(NfsUrl::parse("nfs://h/x?machine=%-1").is_err());
+    }
+
+    #[test]
+    fn rejects_encoded_slashes_in_the_path() {
+        assert!(NfsUrl::parse("nfs://h/export%2Fphotos").is_err());
+        assert!(NfsUrl::parse("nfs://h/a/%2f/b").is_err());
+        let url = NfsUrl::parse("nfs://h/x?machine=a%2Fb").unwrap();
+        assert_eq!(url.query, vec![("machine".to_string(), "a/b".to_string())]);
+    }
+
+    #[test]
+    fn rejects_other_schemes_and_user_info() {
+        assert!(NfsUrl::parse("http://h/export").is_err());
+        assert!(NfsUrl::parse("nas.local:/export").is_err());
+        assert!(NfsUrl::parse("nfs://user@h/export").is_err());
+        assert!(NfsUrl::parse("nfs:///export").is_err());
+    }
+}
@@ -0,0 +1,73 @@
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+
+use jni::sys::jlong;
+use nfscrs::OpenedFile;
+use nfscrs::nfscrs_error::NFSCRSError;
+
+use crate::chunked_io::IoSizes;
+use crate::read_ahead::ReadAhead;
+use crate::session::SessionGuard;
+use crate::write_behind::WriteBehind;
+
+/// The object behind the `opened_file` handle passed to and from Java.
+pub struct FileHandle {
+    file: Mutex<OpenedFile>,
+    pub read_ahead: ReadAhead,
+    pub write_behind: WriteBehind,
+    io_sizes: OnceLock<IoSizes>,
+}
+
+impl FileHandle {
+    pub fn new(file: OpenedFile) -> FileHandle {
+        FileHandle {
+            file: Mutex::new(file),
+            read_ahead: ReadAhead::new(),
+            write_behind: WriteBehind::default(),
+            io_sizes: OnceLock::new(),
+        }
+    }
+
+    /// READ/WRITE sizes for this file (see `IoSizes::query`), queried from
+    /// the server on first use.
+    pub fn io_sizes(
+        &self,
+        session_ref: &mut SessionGuard,
+        opened_file_ref: &OpenedFile,
+    ) -> Result<IoSizes, NFSCRSError> {
+        if let Some(io_sizes) = self.io_sizes.get() {
+            return Ok(*io_sizes);
+        }
+        let io_sizes = IoSizes::query(session_ref, &opened_file_ref.path)?;
+        Ok(*self.io_sizes.get_or_init(|| io_sizes))
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, OpenedFile> {
+        self.file.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn into_jlong(self) -> jlong {
+        Arc::into_raw(Arc::new(self)) as jlong
+    }
+}
+
+/// Resolves an `opened_file` handle returned by `openFile`, taking a new
+/// reference so background work can outlive the JNI call.
+///
+/// # Safety
+/// `opened_file` must be a value returned by [`FileHandle::into_jlong`] that
+/// has not been passed to [`release_file_handle`].
+pub unsafe fn file_handle(opened_file: jlong) -> Arc<FileHandle> {
+    let ptr = opened_file as *const FileHandle;
+    unsafe {
+        Arc::increment_strong_count(ptr);
+        Arc::from_raw(ptr)
+    }
+}
+
+/// Drops the reference held by Java for an `opened_file` handle.
+///
+/// # Safety
+/// Same as [`file_handle`]; the handle must not be used afterwards.
+pub unsafe fn release_file_handle(opened_file: jlong) {
+    unsafe { drop(Arc::from_raw(opened_file as *const FileHandle)) }
+}
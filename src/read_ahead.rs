@@ -0,0 +1,330 @@
+use std::sync::mpsc::{Sender, channel};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, Weak};
+use std::time::Duration;
+
+use nfscrs::nfscrs_error::NFSCRSError;
+
+use crate::attr_utils::{change_bitmap, get_change_attr};
+use crate::chunked_io::read_chunked;
+use crate::opened_file::FileHandle;
+use crate::retry::retry_idempotent;
+use crate::session::SessionHandle;
+
+pub const DEFAULT_READ_AHEAD_MIN_WINDOW: usize = 128 * 1024;
+pub const DEFAULT_READ_AHEAD_MAX_WINDOW: usize = 1024 * 1024;
+
+/// Number of back-to-back sequential reads before prefetching starts.
+const SEQUENTIAL_READS_THRESHOLD: u32 = 2;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ReadAheadConfig {
+    /// First prefetch size once sequential access is detected.
+    pub min_window: usize,
+    /// The window doubles on every prefetch up to this size. Zero disables
+    /// read-ahead.
+    pub max_window: usize,
+}
+
+impl Default for ReadAheadConfig {
+    fn default() -> Self {
+        ReadAheadConfig {
+            min_window: DEFAULT_READ_AHEAD_MIN_WINDOW,
+            max_window: DEFAULT_READ_AHEAD_MAX_WINDOW,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PrefetchRequest {
+    pub offset: usize,
+    pub len: usize,
+    generation: u64,
+}
+
+/// Outcome of `ReadAhead::take`.
+pub enum Take {
+    Hit(CachedRead),
+    /// Not cached and not being prefetched.
+    Miss,
+    /// A prefetch that will cover the offset is still running.
+    Pending,
+}
+
+pub struct CachedRead {
+    pub data: Vec<u8>,
+    pub eof: bool,
+}
+
+struct Prefetched {
+    data: Vec<u8>,
+    eof: bool,
+    change: Option<u64>,
+}
+
+#[derive(Default)]
+struct ReadAheadState {
+    config: ReadAheadConfig,
+    next_offset: usize,
+    sequential_reads: u32,
+    window: usize,
+    /// File offset of `cache[0]`.
+    cache_offset: usize,
+    cache: Vec<u8>,
+    /// The cache ends at end of file.
+    cache_eof: bool,
+    /// Change attribute observed when the cached data was fetched.
+    change: Option<u64>,
+    /// End offset of the in-flight prefetch, if any.
+    prefetch_end: Option<usize>,
+    /// Bumped on invalidation so late prefetch replies are dropped.
+    generation: u64,
+}
+
+impl ReadAheadState {
+    fn cache_end(&self) -> usize {
+        self.cache_offset + self.cache.len()
+    }
+
+    fn clear(&mut self) {
+        self.cache.clear();
+        self.cache_eof = false;
+        self.change = None;
+        self.sequential_reads = 0;
+        self.window = self.config.min_window;
+        self.generation += 1;
+    }
+}
+
+/// Sequential read-ahead cache attached to each opened file.
+///
+/// Once reads arrive back to back, the next window is fetched by the file's
+/// prefetch worker and later `fileRead` calls are served from memory.
+pub struct ReadAhead {
+    state: Mutex<ReadAheadState>,
+    prefetch_done: Condvar,
+    /// Queue of the file's prefetch worker, started on first use. The
+    /// worker exits once the file is dropped, which drops this sender.
+    worker: Mutex<Option<Sender<PrefetchRequest>>>,
+}
+
+impl Default for ReadAhead {
+    fn default() -> Self {
+        ReadAhead::new()
+    }
+}
+
+impl ReadAhead {
+    pub fn new() -> ReadAhead {
+        ReadAhead {
+            state: Mutex::new(ReadAheadState {
+                window: DEFAULT_READ_AHEAD_MIN_WINDOW,
+                ..Default::default()
+            }),
+            prefetch_done: Condvar::new(),
+            worker: Mutex::new(None),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ReadAheadState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn set_config(&self, config: ReadAheadConfig) {
+        let mut state = self.lock();
+        state.config = config;
+        state.clear();
+    }
+
+    /// Drops all cached data, e.g. after the file was written.
+    pub fn invalidate(&self) {
+        self.lock().clear();
+    }
+
+    /// Serves up to `len` bytes at `offset` from the cache. When an in-flight
+    /// prefetch will cover `offset`, waits up to `wait` for it and returns
+    /// `Pending` if it is still running, so that the caller can check for
+    /// interrupts (see `wait_interruptibly`).
+    pub fn take(&self, offset: usize, len: usize, wait: Duration) -> Take {
+        let mut state = self.lock();
+        let mut waited = false;
+        loop {
+            if offset >= state.cache_offset && offset < state.cache_end() {
+                let start = offset - state.cache_offset;
+                let n = len.min(state.cache.len() - start);
+                let data = state.cache[start..start + n].to_vec();
+                state.cache.drain(..start + n);
+                state.cache_offset = offset + n;
+                let eof = state.cache_eof && state.cache.is_empty();
+                return Take::Hit(CachedRead { data, eof });
+            }
+            match state.prefetch_end {
+                Some(end) if offset >= state.cache_end() && offset < end => {
+                    if waited {
+                        return Take::Pending;
+                    }
+                    state = self
+                        .prefetch_done
+                        .wait_timeout(state, wait)
+                        .unwrap_or_else(|e| e.into_inner())
+                        .0;
+                    waited = true;
+                }
+                _ => return Take::Miss,
+            }
+        }
+    }
+
+    /// Records a completed read and decides whether to prefetch the next
+    /// window.
+    pub fn record_read(&self, offset: usize, count: usize, eof: bool) -> Option<PrefetchRequest> {
+        let mut state = self.lock();
+        if offset == state.next_offset {
+            state.sequential_reads += 1;
+        } else {
+            state.clear();
+        }
+        state.next_offset = offset + count;
+
+        if state.config.max_window == 0
+            || state.sequential_reads < SEQUENTIAL_READS_THRESHOLD
+            || state.prefetch_end.is_some()
+            || eof
+            || state.cache_eof
+        {
+            return None;
+        }
+
+        if state.cache.is_empty() {
+            state.cache_offset = state.next_offset;
+        }
+        let unread = state.cache_end().saturating_sub(state.next_offset);
+        if unread > state.window / 2 {
+            return None;
+        }
+
+        let request = PrefetchRequest {
+            offset: state.cache_end(),
+            len: state.window,
+            generation: state.generation,
+        };
+        state.prefetch_end = Some(request.offset + request.len);
+        state.window = (state.window * 2).clamp(state.config.min_window, state.config.max_window);
+        Some(request)
+    }
+
+    fn store(&self, request: &PrefetchRequest, result: Result<Prefetched, NFSCRSError>) {
+        let mut state = self.lock();
+        state.prefetch_end = None;
+        match result {
+            Ok(prefetched) if request.generation == state.generation => {
+                if state.change.is_some() && prefetched.change != state.change {
+                    tracing::debug!("read-ahead: change attribute mismatch, dropping cache");
+                    state.cache.clear();
+                }
+                if state.cache.is_empty() {
+                    state.cache_offset = request.offset;
+                }
+                if state.cache_end() == request.offset {
+                    state.cache.extend_from_slice(&prefetched.data);
+                    state.cache_eof = prefetched.eof;
+                    state.change = prefetched.change;
+                }
+            }
+            Ok(_) => {}
+            Err(e) => tracing::debug!("read-ahead failed: {e}"),
+        }
+        self.prefetch_done.notify_all();
+    }
+
+    /// Blocks until no prefetch is in flight.
+    pub fn wait_idle(&self) {
+        let mut state = self.lock();
+        while state.prefetch_end.is_some() {
+            state = self
+                .prefetch_done
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+    }
+}
+
+/// Queues `request` on the file's prefetch worker, starting the worker on
+/// first use (or again if it died).
+pub fn spawn_prefetch(
+    session_handle: &'static SessionHandle,
+    file_handle: Arc<FileHandle>,
+    request: PrefetchRequest,
+) {
+    let mut worker = file_handle
+        .read_ahead
+        .worker
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    let request = match worker.as_ref().map(|sender| sender.send(request)) {
+        Some(Ok(())) => return,
+        Some(Err(e)) => e.0,
+        None => request,
+    };
+    let (sender, receiver) = channel::<PrefetchRequest>();
+    // The worker only holds a weak reference, so that an idle worker does
+    // not keep a closed file alive.
+    let file = Arc::downgrade(&file_handle);
+    std::thread::spawn(move || {
+        while let Ok(request) = receiver.recv() {
+            let Some(file_handle) = Weak::upgrade(&file) else {
+                break;
+            };
+            let policy = session_handle.policy();
+            let result = retry_idempotent(&policy, "prefetch", || {
+                prefetch(session_handle, &file_handle, &request)
+            });
+            file_handle.read_ahead.store(&request, result);
+        }
+    });
+    let _ = sender.send(request);
+    *worker = Some(sender);
+}
+
+fn prefetch(
+    session_handle: &SessionHandle,
+    file_handle: &FileHandle,
+    request: &PrefetchRequest,
+) -> Result<Prefetched, NFSCRSError> {
+    let mut session_ref = session_handle.lock();
+    let mut opened_file_ref = file_handle.lock();
+    // A failed flush is left to the next Java call on the file to throw;
+    // without it the READs could return stale data.
+    if !file_handle
+        .write_behind
+        .flush_deferred(&mut session_ref, &mut opened_file_ref)
+    {
+        return Err(NFSCRSError::OperationError(
+            "pending writes could not be flushed".to_string(),
+        ));
+    }
+    let io_sizes = file_handle.io_sizes(&mut session_ref, &opened_file_ref)?;
+    // The change attribute comes back in the first READ's compound rather
+    // than costing a GETATTR of its own.
+    let first_len = request.len.min(io_sizes.max_read);
+    let (first, fattr4) = session_ref.read_with_attrs(
+        &mut opened_file_ref,
+        request.offset,
+        first_len,
+        change_bitmap(),
+    )?;
+    let change = get_change_attr(&fattr4);
+    let mut data = first.data;
+    let mut eof = first.eof || data.is_empty();
+    if !eof && data.len() < request.len {
+        let rest = read_chunked(
+            &mut session_ref,
+            &mut opened_file_ref,
+            request.offset + data.len(),
+            request.len - data.len(),
+            io_sizes.max_read,
+        )?;
+        data.extend_from_slice(&rest.data);
+        eof = rest.eof;
+    }
+    Ok(Prefetched { data, eof, change })
+}
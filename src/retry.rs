@@ -0,0 +1,280 @@
+use std::fmt::Debug;
+use std::time::Duration;
+
+use jni::JNIEnv;
+use nfscrs::nfs4_types::NFSStat4;
+use nfscrs::nfscrs_error::NFSCRSError;
+
+use crate::error::NfscrsJniError;
+use crate::interrupt::{interruptible, with_deadline};
+use crate::session::{SessionGuard, SessionHandle};
+
+/// Upper bound for the doubling backoff between retries.
+const MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Deadline and retry behaviour of the operations on a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OperationPolicy {
+    /// How long a Java call waits for its operation, retries included,
+    /// before throwing `SocketTimeoutException`; also the transport timeout
+    /// of each RPC. `None` waits forever.
+    pub timeout: Option<Duration>,
+    /// Times an operation is re-sent after NFS4ERR_DELAY or NFS4ERR_GRACE,
+    /// or, for operations that only read, after a transport error.
+    pub max_retries: u32,
+    /// Wait before the first retry, doubled after each one up to
+    /// `MAX_BACKOFF`.
+    pub initial_backoff: Duration,
+}
+
+impl Default for OperationPolicy {
+    /// No deadline and no retries, as before policies existed.
+    fn default() -> OperationPolicy {
+        OperationPolicy {
+            timeout: None,
+            max_retries: 0,
+            initial_backoff: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Errors after which an operation may be sent again.
+pub trait Retryable {
+    /// The server did not perform the operation, so any operation may be
+    /// re-sent.
+    fn is_retryable(&self) -> bool;
+    /// The request or its reply was lost in transport. The server may have
+    /// performed the operation, so only idempotent operations are re-sent.
+    fn is_transient(&self) -> bool;
+}
+
+/// NFS4ERR_DELAY and NFS4ERR_GRACE mean the server did not perform the
+/// operation, so re-sending is safe even for opens and creates.
+impl Retryable for NFSCRSError {
+    fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            NFSCRSError::NFSStatError(NFSStat4::NFS4ERR_DELAY | NFSStat4::NFS4ERR_GRACE)
+        )
+    }
+
+    fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            NFSCRSError::Connection(_)
+                | NFSCRSError::ReadMessage(_)
+                | NFSCRSError::SendMessage(_)
+                | NFSCRSError::EmptyReplyBody
+        )
+    }
+}
+
+impl Retryable for NfscrsJniError {
+    fn is_retryable(&self) -> bool {
+        matches!(self, NfscrsJniError::NFSCRSError(e) if e.is_retryable())
+    }
+
+    fn is_transient(&self) -> bool {
+        matches!(self, NfscrsJniError::NFSCRSError(e) if e.is_transient())
+    }
+}
+
+/// Runs `op`, running it again with exponential backoff while it fails with
+/// a retryable error and `policy` allows more attempts.
+///
+/// `op` takes the session and file locks it needs itself, so that nothing
+/// is held while sleeping and other callers of the session keep going
+/// during the backoff.
+pub fn retry<T, E: Retryable + Debug>(
+    policy: &OperationPolicy,
+    name: &str,
+    op: impl FnMut() -> Result<T, E>,
+) -> Result<T, E> {
+    retry_while(policy, name, E::is_retryable, op)
+}
+
+/// Like `retry`, but also re-sends after transport errors. Only for
+/// operations that can safely be performed twice, such as READ or GETATTR.
+pub fn retry_idempotent<T, E: Retryable + Debug>(
+    policy: &OperationPolicy,
+    name: &str,
+    op: impl FnMut() -> Result<T, E>,
+) -> Result<T, E> {
+    retry_while(
+        policy,
+        name,
+        |e: &E| e.is_retryable() || e.is_transient(),
+        op,
+    )
+}
+
+fn retry_while<T, E: Debug>(
+    policy: &OperationPolicy,
+    name: &str,
+    should_retry: impl Fn(&E) -> bool,
+    mut op: impl FnMut() -> Result<T, E>,
+) -> Result<T, E> {
+    let mut backoff = policy.initial_backoff;
+    let mut attempt = 0;
+    loop {
+        match op() {
+            Err(e) if attempt < policy.max_retries && should_retry(&e) => {
+                attempt += 1;
+                tracing::debug!(
+                    "{name}: {e:?}, retry {attempt}/{} in {backoff:?}",
+                    policy.max_retries
+                );
+                std::thread::sleep(backoff);
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Runs `op` for a Java call under the policy of `session_handle`: retried
+/// as by `retry` with the session locked afresh for each attempt, and
+/// abandoned at the policy's deadline (see `with_deadline`). For operations
+/// that create or change state.
+pub fn call_with_deadline<T, F>(
+    env: &mut JNIEnv,
+    session_handle: &'static SessionHandle,
+    name: &'static str,
+    mut op: F,
+) -> Result<T, NfscrsJniError>
+where
+    T: Send + 'static,
+    F: FnMut(&mut SessionGuard) -> Result<T, NfscrsJniError> + Send + 'static,
+{
+    let policy = session_handle.policy();
+    with_deadline(env, name, policy.timeout, move || {
+        retry(&policy, name, || op(&mut session_handle.lock()))
+    })
+}
+
+/// Like `call_with_deadline`, but always `interruptible`, and transport
+/// errors are retried as by `retry_idempotent`. For operations that only
+/// read and are safe to abandon or repeat.
+pub fn call_interruptible<T, F>(
+    env: &mut JNIEnv,
+    session_handle: &'static SessionHandle,
+    name: &'static str,
+    mut op: F,
+) -> Result<T, NfscrsJniError>
+where
+    T: Send + 'static,
+    F: FnMut(&mut SessionGuard) -> Result<T, NfscrsJniError> + Send + 'static,
+{
+    let policy = session_handle.policy();
+    interruptible(env, name, policy.timeout, move || {
+        retry_idempotent(&policy, name, || op(&mut session_handle.lock()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(max_retries: u32) -> OperationPolicy {
+        OperationPolicy {
+            timeout: None,
+            max_retries,
+            initial_backoff: Duration::from_millis(1),
+        }
+    }
+
+    fn delay() -> NFSCRSError {
+        NFSCRSError::NFSStatError(NFSStat4::NFS4ERR_DELAY)
+    }
+
+    #[test]
+    fn retries_delay_and_grace_up_to_max_retries() {
+        let mut calls = 0;
+        let result: Result<(), _> = retry(&policy(3), "op", || {
+            calls += 1;
+            Err(delay())
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 4);
+
+        let mut calls = 0;
+        let result = retry(&policy(3), "op", || {
+            calls += 1;
+            match calls {
+                1 => Err(NFSCRSError::NFSStatError(NFSStat4::NFS4ERR_GRACE)),
+                2 => Err(delay()),
+                _ => Ok(calls),
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[test]
+    fn does_not_retry_other_errors() {
+        for err in [
+            NFSCRSError::NFSStatError(NFSStat4::NFS4ERR_NOENT),
+            NFSCRSError::EmptyReplyBody,
+        ] {
+            let mut err = Some(err);
+            let mut calls = 0;
+            let result: Result<(), _> = retry(&policy(3), "op", || {
+                calls += 1;
+                Err(err.take().unwrap_or_else(delay))
+            });
+            assert!(result.is_err());
+            assert_eq!(calls, 1);
+        }
+    }
+
+    #[test]
+    fn zero_retries_runs_once() {
+        let mut calls = 0;
+        let result: Result<(), _> = retry(&policy(0), "op", || {
+            calls += 1;
+            Err(delay())
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn retries_transport_errors_only_when_idempotent() {
+        let mut calls = 0;
+        let result = retry_idempotent(&policy(3), "op", || {
+            calls += 1;
+            match calls {
+                1 => Err(NFSCRSError::EmptyReplyBody),
+                2 => Err(delay()),
+                _ => Ok(calls),
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+
+        let mut calls = 0;
+        let result: Result<(), _> = retry_idempotent(&policy(3), "op", || {
+            calls += 1;
+            Err(NFSCRSError::NFSStatError(NFSStat4::NFS4ERR_NOENT))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn retries_wrapped_nfs_errors_only() {
+        let mut calls = 0;
+        let result: Result<(), NfscrsJniError> = retry(&policy(2), "op", || {
+            calls += 1;
+            Err(delay().into())
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 3);
+
+        let mut calls = 0;
+        let result: Result<(), NfscrsJniError> = retry(&policy(2), "op", || {
+            calls += 1;
+            Err(NfscrsJniError::Timeout("op".to_string()))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+}
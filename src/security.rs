@@ -0,0 +1,164 @@
+use jni::JNIEnv;
+use jni::objects::{JObject, JString, JValue};
+use jni::sys::{jlong, jobject};
+use nfscrs::NFSClientSession;
+use nfscrs::nfs4_types::{RpcGssSvc, SecInfo4};
+use nfscrs::nfscrs_error::NFSCRSError;
+use nfscrs::nfscrs_types::AbsolutePath;
+
+use crate::error::{NfscrsJniError, handle_error, is_not_supported};
+use crate::jni_utils::{as_class, jni_cache};
+use crate::retry::call_interruptible;
+use crate::session::{SessionHandle, session_handle};
+
+const AUTH_NONE: u32 = 0;
+const AUTH_SYS: u32 = 1;
+const RPCSEC_GSS: u32 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecFlavor {
+    None,
+    Sys,
+    Krb5,
+    Krb5i,
+    Krb5p,
+    Other(u32),
+}
+
+impl SecFlavor {
+    fn from_secinfo(info: &SecInfo4) -> SecFlavor {
+        match (info.flavor, &info.flavor_info) {
+            (AUTH_NONE, _) => SecFlavor::None,
+            (AUTH_SYS, _) => SecFlavor::Sys,
+            (RPCSEC_GSS, Some(gss)) => match gss.service {
+                RpcGssSvc::RPC_GSS_SVC_NONE => SecFlavor::Krb5,
+                RpcGssSvc::RPC_GSS_SVC_INTEGRITY => SecFlavor::Krb5i,
+                RpcGssSvc::RPC_GSS_SVC_PRIVACY => SecFlavor::Krb5p,
+            },
+            (flavor, _) => SecFlavor::Other(flavor),
+        }
+    }
+
+    /// Name as used in the `sec=` mount option.
+    pub fn name(&self) -> String {
+        match self {
+            SecFlavor::None => "none".to_string(),
+            SecFlavor::Sys => "sys".to_string(),
+            SecFlavor::Krb5 => "krb5".to_string(),
+            SecFlavor::Krb5i => "krb5i".to_string(),
+            SecFlavor::Krb5p => "krb5p".to_string(),
+            SecFlavor::Other(flavor) => format!("flavor-{flavor}"),
+        }
+    }
+}
+
+/// Flavors the server accepts for `path`, in its order of preference
+/// (SECINFO). `None` when the server does not implement SECINFO.
+pub fn security_flavors(
+    session_ref: &mut NFSClientSession,
+    path: &AbsolutePath,
+) -> Result<Option<Vec<SecFlavor>>, NFSCRSError> {
+    match session_ref.secinfo(path) {
+        Ok(infos) => Ok(Some(infos.iter().map(SecFlavor::from_secinfo).collect())),
+        Err(e) if is_not_supported(&e) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Flavors this client is known not to be able to use: nfscrs has no
+/// RPCSEC_GSS to set up a krb5 context with.
+const UNUSABLE_FLAVORS: [SecFlavor; 3] = [SecFlavor::Krb5, SecFlavor::Krb5i, SecFlavor::Krb5p];
+
+/// Fails when every flavor the server lists for `path` is in
+/// `UNUSABLE_FLAVORS`, which reports `sec=krb5*` exports clearly when the
+/// session is established.
+///
+/// The check is best effort: a failing SECINFO is logged and ignored, and
+/// flavors this client does not know keep the session. An operation the
+/// server then rejects with NFS4ERR_WRONGSEC throws `SecurityException`
+/// (see `throw_nfs_error`).
+pub fn check_security(
+    session_ref: &mut NFSClientSession,
+    path: &AbsolutePath,
+) -> Result<(), NfscrsJniError> {
+    let flavors = match security_flavors(session_ref, path) {
+        Ok(Some(flavors)) => flavors,
+        Ok(None) => return Ok(()),
+        Err(e) => {
+            tracing::debug!("SECINFO of {:?} failed, not checking flavors: {e}", path);
+            return Ok(());
+        }
+    };
+    tracing::debug!("security flavors of {:?}: {:?}", path, flavors);
+    if flavors.is_empty() || !flavors.iter().all(|f| UNUSABLE_FLAVORS.contains(f)) {
+        return Ok(());
+    }
+    let names: Vec<String> = flavors.iter().map(SecFlavor::name).collect();
+    Err(NfscrsJniError::UnsupportedOperation(format!(
+        "server requires sec={}; only AUTH_SYS is supported",
+        names.join(":")
+    )))
+}
+
+/// Returns the security flavors the server accepts for `path` as a
+/// `List<String>` of `sec=` names (`sys`, `krb5`, `krb5i`, `krb5p`, ...),
+/// or null when the server does not implement SECINFO.
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_listSecurityFlavors(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    path: JString,
+) -> jobject {
+    let session_handle = unsafe { session_handle(session) };
+    match list_security_flavors(session_handle, &mut env, &path) {
+        Ok(r) => r,
+        Err(e) => {
+            handle_error(&mut env, &e);
+            return std::ptr::null_mut();
+        }
+    }
+}
+
+fn list_security_flavors(
+    session_handle: &'static SessionHandle,
+    env: &mut JNIEnv,
+    path: &JString,
+) -> Result<jobject, NfscrsJniError> {
+    let path_str: String = env.get_string(path)?.into();
+    let flavors = call_interruptible(
+        env,
+        session_handle,
+        "listSecurityFlavors",
+        move |session_ref| {
+            let abs_path = session_ref.resolve(path_str.clone())?;
+            Ok(security_flavors(session_ref, &abs_path)?)
+        },
+    )?;
+    let Some(flavors) = flavors else {
+        return Ok(std::ptr::null_mut());
+    };
+
+    let cache = jni_cache()?;
+    let array_list_obj = unsafe {
+        env.new_object_unchecked(
+            as_class(&cache.array_list_class),
+            cache.array_list_ctor,
+            &[],
+        )
+    }?;
+    for flavor in flavors {
+        let jname = env.new_string(flavor.name())?;
+        unsafe {
+            env.call_method_unchecked(
+                &array_list_obj,
+                cache.array_list_add,
+                jni::signature::ReturnType::Primitive(jni::signature::Primitive::Boolean),
+                &[JValue::Object(&jname).as_jni()],
+            )
+        }?;
+        env.delete_local_ref(jname)?;
+    }
+    Ok(array_list_obj.into_raw())
+}
@@ -0,0 +1,370 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use jni::sys::jlong;
+use nfscrs::NFSClientSession;
+use nfscrs::nfscrs_error::NFSCRSError;
+use nfscrs::nfscrs_types::AbsolutePath;
+
+use crate::chunked_io::IoSizeLimits;
+use crate::connect::establish;
+use crate::credential::AuthSysCredential;
+use crate::error::NfscrsJniError;
+use crate::retry::OperationPolicy;
+use crate::session_options::SessionOptions;
+
+struct SessionState {
+    session: NFSClientSession,
+    /// Credential currently installed in `session`.
+    active: AuthSysCredential,
+    /// RPC timeout currently installed in `session`.
+    rpc_timeout: Option<Duration>,
+}
+
+/// Views a session can have. Views are never released, so this bounds the
+/// memory a session can hold on to; callers that need many credentials or
+/// timeouts should reuse their views.
+pub const MAX_VIEWS: usize = 256;
+
+struct SharedSession {
+    state: Mutex<SessionState>,
+    /// Absolute server path that Java paths are relative to, e.g. the
+    /// export of an `nfs://` URL. `/` for the pseudo-root. nfscrs addresses
+    /// files by path, so the root is prepended to every path rather than
+    /// kept as a filehandle; it is checked once when the session is made.
+    root: String,
+    /// Views created so far; see [`SessionHandle::view`].
+    views: Mutex<Vec<&'static SessionHandle>>,
+    /// Shared by all handles of the session but policy views.
+    policy: Mutex<OperationPolicy>,
+    io_size_limits: IoSizeLimits,
+    /// How to connect further sessions to the same server, for the streams
+    /// of a parallel transfer. `None` for sessions over a socket handed in
+    /// from Java, which cannot be reconnected.
+    connect_options: Option<SessionOptions>,
+}
+
+/// Numbers the client owners of stream sessions, which must differ from
+/// each other and from the session's own.
+static NEXT_STREAM: AtomicU64 = AtomicU64::new(0);
+
+/// The object behind the `session` handle passed to and from Java.
+///
+/// The NFS session is guarded by a mutex so that native background work
+/// (read-ahead) can share it with calls coming from Java threads. Several
+/// handles may share one NFS session, each with its own credential and
+/// possibly its own policy; the credential and the policy's timeout, as the
+/// RPC timeout, are installed whenever the handle takes the lock.
+pub struct SessionHandle {
+    shared: Arc<SharedSession>,
+    credential: Mutex<AuthSysCredential>,
+    /// Policy of a policy view, used instead of the shared one.
+    policy: Option<OperationPolicy>,
+    is_view: bool,
+}
+
+/// Locked NFS session, dereferencing to [`NFSClientSession`].
+pub struct SessionGuard<'a> {
+    state: MutexGuard<'a, SessionState>,
+    root: &'a str,
+    policy: OperationPolicy,
+    io_size_limits: IoSizeLimits,
+}
+
+impl SessionGuard<'_> {
+    /// The session's policy as of taking the lock.
+    pub fn policy(&self) -> OperationPolicy {
+        self.policy
+    }
+
+    /// READ/WRITE sizes requested when the session was created.
+    pub fn io_size_limits(&self) -> IoSizeLimits {
+        self.io_size_limits
+    }
+
+    /// Turns a path from Java into a server path under the session root.
+    pub fn resolve(&self, path: String) -> Result<AbsolutePath, NfscrsJniError> {
+        resolve_under_root(self.root, path)
+    }
+}
+
+impl Deref for SessionGuard<'_> {
+    type Target = NFSClientSession;
+
+    fn deref(&self) -> &NFSClientSession {
+        &self.state.session
+    }
+}
+
+impl DerefMut for SessionGuard<'_> {
+    fn deref_mut(&mut self) -> &mut NFSClientSession {
+        &mut self.state.session
+    }
+}
+
+impl SessionHandle {
+    /// `credential` must be the one the session was established with;
+    /// `root` is a normalized absolute path (see [`join_under_root`]).
+    pub fn new(
+        session: NFSClientSession,
+        credential: AuthSysCredential,
+        root: String,
+        policy: OperationPolicy,
+        io_size_limits: IoSizeLimits,
+        connect_options: Option<SessionOptions>,
+    ) -> SessionHandle {
+        SessionHandle {
+            shared: Arc::new(SharedSession {
+                state: Mutex::new(SessionState {
+                    session,
+                    active: credential.clone(),
+                    rpc_timeout: None,
+                }),
+                views: Mutex::new(Vec::new()),
+                root,
+                policy: Mutex::new(policy),
+                io_size_limits,
+                connect_options,
+            }),
+            credential: Mutex::new(credential),
+            policy: None,
+            is_view: false,
+        }
+    }
+
+    /// Locks the session, installing this handle's credential and RPC
+    /// timeout when another handle left different ones.
+    ///
+    /// The RPC timeout makes a hung request fail with a transport error
+    /// instead of holding the lock forever, so an operation abandoned at
+    /// its deadline (see `interruptible`) releases the session soon after.
+    pub fn lock(&self) -> SessionGuard<'_> {
+        let mut state = self.shared.state.lock().unwrap_or_else(|e| e.into_inner());
+        let credential = self.credential.lock().unwrap_or_else(|e| e.into_inner());
+        if state.active != *credential {
+            credential.apply(&mut state.session);
+            state.active = credential.clone();
+        }
+        let policy = self.policy();
+        if state.rpc_timeout != policy.timeout {
+            state.session.set_rpc_timeout(policy.timeout);
+            state.rpc_timeout = policy.timeout;
+        }
+        SessionGuard {
+            state,
+            root: &self.shared.root,
+            policy,
+            io_size_limits: self.shared.io_size_limits,
+        }
+    }
+
+    pub fn policy(&self) -> OperationPolicy {
+        match self.policy {
+            Some(policy) => policy,
+            None => *self.shared.policy.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+
+    /// Replaces the policy of the session, for this handle and its
+    /// credential views, from the next call on. Policy views keep theirs.
+    pub fn set_policy(&self, policy: OperationPolicy) {
+        *self.shared.policy.lock().unwrap_or_else(|e| e.into_inner()) = policy;
+    }
+
+    /// Replaces the credential used by this handle from its next RPC on.
+    /// Views are bound to one credential and cannot be changed.
+    pub fn set_credential(&self, credential: AuthSysCredential) -> Result<(), NfscrsJniError> {
+        if self.is_view {
+            return Err(NfscrsJniError::IllegalArgument(
+                "cannot change the credential of a session view".to_string(),
+            ));
+        }
+        *self.credential.lock().unwrap_or_else(|e| e.into_inner()) = credential;
+        Ok(())
+    }
+
+    /// Returns a handle to the same NFS session that sends `credential`,
+    /// with this handle's policy.
+    ///
+    /// Views live as long as their session, so one view is kept per
+    /// distinct credential and policy and handed out again on later calls,
+    /// up to `MAX_VIEWS`. A view without a machine name inherits this
+    /// handle's.
+    pub fn view(
+        &self,
+        mut credential: AuthSysCredential,
+    ) -> Result<&'static SessionHandle, NfscrsJniError> {
+        if credential.machine_name.is_none() {
+            credential.machine_name = self
+                .credential
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .machine_name
+                .clone();
+        }
+        self.find_or_create_view(credential, self.policy)
+    }
+
+    /// Returns a handle to the same NFS session with this handle's
+    /// credential whose calls follow `policy` rather than the session's.
+    /// Counts towards `MAX_VIEWS` like `view`.
+    pub fn policy_view(
+        &self,
+        policy: OperationPolicy,
+    ) -> Result<&'static SessionHandle, NfscrsJniError> {
+        let credential = self
+            .credential
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        self.find_or_create_view(credential, Some(policy))
+    }
+
+    fn find_or_create_view(
+        &self,
+        credential: AuthSysCredential,
+        policy: Option<OperationPolicy>,
+    ) -> Result<&'static SessionHandle, NfscrsJniError> {
+        let mut views = self.shared.views.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(view) = views.iter().copied().find(|v| {
+            v.policy == policy
+                && *v.credential.lock().unwrap_or_else(|e| e.into_inner()) == credential
+        }) {
+            return Ok(view);
+        }
+        if views.len() >= MAX_VIEWS {
+            return Err(NfscrsJniError::IllegalArgument(format!(
+                "session already has {MAX_VIEWS} views; reuse an existing view"
+            )));
+        }
+        let view: &'static SessionHandle = Box::leak(Box::new(SessionHandle {
+            shared: self.shared.clone(),
+            credential: Mutex::new(credential),
+            policy,
+            is_view: true,
+        }));
+        views.push(view);
+        Ok(view)
+    }
+
+    /// Whether `connect_stream` can open further connections.
+    pub fn can_connect_streams(&self) -> bool {
+        self.shared.connect_options.is_some()
+    }
+
+    /// Connects a separate NFS session to the same server, for one stream of
+    /// a parallel transfer: a session has a single slot, so RPCs on it never
+    /// overlap. The stream gets a client owner of its own, lest the server
+    /// take it for a restart of this client, and this handle's credential
+    /// and RPC timeout. Dropping it closes the connection; its lease then
+    /// expires on the server.
+    pub fn connect_stream(&self) -> Result<NFSClientSession, NfscrsJniError> {
+        let Some(options) = &self.shared.connect_options else {
+            return Err(NfscrsJniError::UnsupportedOperation(
+                "a session over a Java socket cannot open more connections".to_string(),
+            ));
+        };
+        let mut options = options.clone();
+        let stream = NEXT_STREAM.fetch_add(1, Ordering::Relaxed);
+        options.client_owner = format!("{}/stream-{stream}", options.client_owner);
+        options.credential = self
+            .credential
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        let mut session = establish(&options)?;
+        session.set_rpc_timeout(self.policy().timeout);
+        Ok(session)
+    }
+
+    /// Identifies the NFS session behind this handle; the same for all of
+    /// its views.
+    pub fn session_key(&self) -> usize {
+        Arc::as_ptr(&self.shared) as usize
+    }
+
+    pub fn into_jlong(self) -> jlong {
+        Box::into_raw(Box::new(self)) as jlong
+    }
+}
+
+/// Resolves a `session` handle returned by `getClientSession`,
+/// `createCredentialView` or `createPolicyView`.
+///
+/// # Safety
+/// `session` must be a value returned by [`SessionHandle::into_jlong`] or
+/// one of the view functions. Sessions and their views are never released, so
+/// the reference lives for the rest of the process.
+pub unsafe fn session_handle(session: jlong) -> &'static SessionHandle {
+    unsafe { &*(session as *const SessionHandle) }
+}
+
+/// Java paths must be absolute, as they were before sessions had a root;
+/// `/` then names `root`. Relative paths fail as `AbsolutePath` rejects
+/// them.
+fn resolve_under_root(root: &str, path: String) -> Result<AbsolutePath, NfscrsJniError> {
+    AbsolutePath::try_from(path.clone()).map_err(NFSCRSError::InnerError)?;
+    let server_path = join_under_root(root, &path)?;
+    AbsolutePath::try_from(server_path).map_err(|e| NFSCRSError::InnerError(e).into())
+}
+
+/// Joins a Java path onto `root`, resolving `.` and `..` on the client.
+/// Paths are taken relative to `root` whether or not they start with `/`,
+/// and `..` may not climb above it.
+pub fn join_under_root(root: &str, path: &str) -> Result<String, NfscrsJniError> {
+    let mut components: Vec<&str> = Vec::new();
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                if components.pop().is_none() {
+                    return Err(NfscrsJniError::IllegalArgument(format!(
+                        "path escapes the session root: {path}"
+                    )));
+                }
+            }
+            c => components.push(c),
+        }
+    }
+    let mut joined = root.trim_end_matches('/').to_string();
+    for component in components {
+        joined.push('/');
+        joined.push_str(component);
+    }
+    if joined.is_empty() {
+        joined.push('/');
+    }
+    Ok(joined)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn join_under_root_normalizes_components() {
+        assert_eq!(join_under_root("/", "/a//b/").unwrap(), "/a/b");
+        assert_eq!(join_under_root("/", "").unwrap(), "/");
+        assert_eq!(join_under_root("/export", "/").unwrap(), "/export");
+        assert_eq!(
+            join_under_root("/export/", "/a/./b/../c").unwrap(),
+            "/export/a/c"
+        );
+        assert_eq!(join_under_root("/export", "a/..").unwrap(), "/export");
+    }
+
+    #[test]
+    fn join_under_root_rejects_escapes() {
+        assert!(join_under_root("/export", "..").is_err());
+        assert!(join_under_root("/export", "/a/../../b").is_err());
+        assert!(join_under_root("/", "/..").is_err());
+    }
+
+    #[test]
+    fn resolve_rejects_relative_paths() {
+        assert!(resolve_under_root("/export", "a/b".to_string()).is_err());
+        assert!(resolve_under_root("/export", "/a/b".to_string()).is_ok());
+    }
+}
@@ -0,0 +1,264 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use jni::JNIEnv;
+use jni::objects::{JIntArray, JObject, JString};
+use nfscrs::NFSClientBuilder;
+
+use crate::chunked_io::IoSizeLimits;
+use crate::credential::AuthSysCredential;
+use crate::error::NfscrsJniError;
+use crate::retry::OperationPolicy;
+use crate::session::join_under_root;
+
+/// Per-address connect timeout when none is configured, so that an
+/// unreachable address does not hold up the next one for the OS default.
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Session minor versions nfscrs can establish (NFSv4.0 has no sessions).
+const SUPPORTED_MINOR_VERSIONS: [u32; 2] = [1, 2];
+
+/// Parsed `com.algebnaly.nfs4c.NFS4SessionOptions`.
+#[derive(Debug, Clone)]
+pub struct SessionOptions {
+    pub credential: AuthSysCredential,
+    /// `host:port` as accepted by `ToSocketAddrs`.
+    pub server_address: String,
+    pub client_owner: String,
+    /// `None` lets nfscrs pick.
+    pub minor_version: Option<u32>,
+    /// Per-address connect timeout; `None` for `DEFAULT_CONNECT_TIMEOUT`.
+    pub connect_timeout: Option<Duration>,
+    /// Server path the session is rooted at; see `SessionGuard::resolve`.
+    pub root: String,
+    pub policy: OperationPolicy,
+    pub io_size_limits: IoSizeLimits,
+}
+
+impl SessionOptions {
+    pub fn new(
+        credential: AuthSysCredential,
+        server_address: String,
+        client_owner: String,
+    ) -> SessionOptions {
+        SessionOptions {
+            credential,
+            server_address,
+            client_owner,
+            minor_version: None,
+            connect_timeout: None,
+            root: "/".to_string(),
+            policy: OperationPolicy::default(),
+            io_size_limits: IoSizeLimits::default(),
+        }
+    }
+
+    /// Reads the public fields of an `NFS4SessionOptions`:
+    ///
+    /// - `int uid`, `int gid`, `int[] gids`, `String machineName`
+    /// - `String serverAddress`, `String clientOwner`
+    /// - `int minorVersion` (0 for the default)
+    /// - `long connectTimeoutMillis` per address (0 for the default)
+    /// - `String rootPath`: export to root the session at (null for `/`).
+    ///   It is prepended to every path, not resolved to a filehandle, so
+    ///   renaming the export or a directory above it affects the session
+    /// - `long rpcTimeoutMillis` per operation (0 for none), `int maxRetries`
+    ///   and `long retryBackoffMillis` (0 for the default); see
+    ///   `OperationPolicy`
+    /// - `int readSize`, `int writeSize`: bytes per READ/WRITE, capped by
+    ///   the server's maxread/maxwrite (0 for the server's maximum)
+    /// - `String secFlavor` (null or `"sys"`)
+    /// - `boolean tls`
+    pub fn from_java(
+        env: &mut JNIEnv,
+        options: &JObject,
+    ) -> Result<SessionOptions, NfscrsJniError> {
+        if options.is_null() {
+            return Err(NfscrsJniError::IllegalArgument(
+                "session options must not be null".to_string(),
+            ));
+        }
+        let uid = env.get_field(options, "uid", "I")?.i()?;
+        let gid = env.get_field(options, "gid", "I")?.i()?;
+        let gids = JIntArray::from(env.get_field(options, "gids", "[I")?.l()?);
+        let machine_name = string_field(env, options, "machineName")?;
+        let credential = AuthSysCredential::from_java(env, uid, gid, &gids, &machine_name)?;
+
+        let server_address = required_string(env, options, "serverAddress")?;
+        let client_owner = required_string(env, options, "clientOwner")?;
+        let mut parsed = SessionOptions::new(credential, server_address, client_owner);
+
+        let minor_version = env.get_field(options, "minorVersion", "I")?.i()?;
+        if minor_version != 0 {
+            if minor_version < 0 || !SUPPORTED_MINOR_VERSIONS.contains(&(minor_version as u32)) {
+                return Err(NfscrsJniError::IllegalArgument(format!(
+                    "unsupported NFSv4 minor version: {minor_version}"
+                )));
+            }
+            parsed.minor_version = Some(minor_version as u32);
+        }
+
+        parsed.connect_timeout = millis_field(env, options, "connectTimeoutMillis")?;
+
+        let root_path = string_field(env, options, "rootPath")?;
+        if !root_path.is_null() {
+            let root_path: String = env.get_string(&root_path)?.into();
+            parsed.root = join_under_root("/", &root_path)?;
+        }
+
+        parsed.policy = policy_from_java(
+            env.get_field(options, "rpcTimeoutMillis", "J")?.j()?,
+            env.get_field(options, "maxRetries", "I")?.i()?,
+            env.get_field(options, "retryBackoffMillis", "J")?.j()?,
+        )?;
+
+        parsed.io_size_limits = IoSizeLimits {
+            read_size: size_field(env, options, "readSize")?,
+            write_size: size_field(env, options, "writeSize")?,
+        };
+
+        let sec_flavor = string_field(env, options, "secFlavor")?;
+        if !sec_flavor.is_null() {
+            let sec_flavor: String = env.get_string(&sec_flavor)?.into();
+            match sec_flavor.as_str() {
+                "sys" => {}
+                "krb5" | "krb5i" | "krb5p" => {
+                    return Err(NfscrsJniError::UnsupportedOperation(format!(
+                        "sec={sec_flavor} is not supported; only AUTH_SYS is available"
+                    )));
+                }
+                _ => {
+                    return Err(NfscrsJniError::IllegalArgument(format!(
+                        "unknown security flavor: {sec_flavor}"
+                    )));
+                }
+            }
+        }
+
+        if env.get_field(options, "tls", "Z")?.z()? {
+            return Err(NfscrsJniError::UnsupportedOperation(
+                "RPC-over-TLS is not supported by the NFS transport".to_string(),
+            ));
+        }
+        Ok(parsed)
+    }
+
+    pub fn builder(&self, addr: SocketAddr) -> NFSClientBuilder {
+        let mut builder = NFSClientBuilder::new(
+            self.credential.uid,
+            self.credential.gid,
+            addr,
+            self.client_owner.as_bytes().to_owned(),
+        );
+        builder = self.credential.configure(builder);
+        if let Some(minor_version) = self.minor_version {
+            builder = builder.minor_version(minor_version);
+        }
+        builder.connect_timeout(self.connect_timeout.unwrap_or(DEFAULT_CONNECT_TIMEOUT))
+    }
+}
+
+fn string_field<'local>(
+    env: &mut JNIEnv<'local>,
+    options: &JObject,
+    name: &str,
+) -> Result<JString<'local>, NfscrsJniError> {
+    Ok(JString::from(
+        env.get_field(options, name, "Ljava/lang/String;")?.l()?,
+    ))
+}
+
+fn required_string(
+    env: &mut JNIEnv,
+    options: &JObject,
+    name: &str,
+) -> Result<String, NfscrsJniError> {
+    let value = string_field(env, options, name)?;
+    if value.is_null() {
+        return Err(NfscrsJniError::IllegalArgument(format!(
+            "{name} must not be null"
+        )));
+    }
+    Ok(env.get_string(&value)?.into())
+}
+
+/// A non-negative millisecond field; 0 means unset.
+fn millis_field(
+    env: &mut JNIEnv,
+    options: &JObject,
+    name: &str,
+) -> Result<Option<Duration>, NfscrsJniError> {
+    let millis = env.get_field(options, name, "J")?.j()?;
+    if millis < 0 {
+        return Err(NfscrsJniError::IllegalArgument(format!(
+            "{name} must not be negative: {millis}"
+        )));
+    }
+    Ok((millis > 0).then(|| Duration::from_millis(millis as u64)))
+}
+
+/// A non-negative byte count field; 0 means unset.
+fn size_field(
+    env: &mut JNIEnv,
+    options: &JObject,
+    name: &str,
+) -> Result<Option<usize>, NfscrsJniError> {
+    let size = env.get_field(options, name, "I")?.i()?;
+    if size < 0 {
+        return Err(NfscrsJniError::IllegalArgument(format!(
+            "{name} must not be negative: {size}"
+        )));
+    }
+    Ok((size > 0).then_some(size as usize))
+}
+
+/// Builds a policy from Java values: zero timeout for none, zero backoff
+/// for the default.
+pub fn policy_from_java(
+    timeout_millis: i64,
+    max_retries: i32,
+    backoff_millis: i64,
+) -> Result<OperationPolicy, NfscrsJniError> {
+    if timeout_millis < 0 || max_retries < 0 || backoff_millis < 0 {
+        return Err(NfscrsJniError::IllegalArgument(format!(
+            "invalid operation policy: timeout {timeout_millis}ms, \
+             {max_retries} retries, backoff {backoff_millis}ms"
+        )));
+    }
+    let mut policy = OperationPolicy {
+        timeout: (timeout_millis > 0).then(|| Duration::from_millis(timeout_millis as u64)),
+        max_retries: max_retries as u32,
+        ..OperationPolicy::default()
+    };
+    if backoff_millis > 0 {
+        policy.initial_backoff = Duration::from_millis(backoff_millis as u64);
+    }
+    Ok(policy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policy_from_java_maps_values() {
+        let policy = policy_from_java(2500, 3, 50).unwrap();
+        assert_eq!(policy.timeout, Some(Duration::from_millis(2500)));
+        assert_eq!(policy.max_retries, 3);
+        assert_eq!(policy.initial_backoff, Duration::from_millis(50));
+    }
+
+    #[test]
+    fn policy_from_java_zero_means_default() {
+        let policy = policy_from_java(0, 0, 0).unwrap();
+        assert_eq!(policy, OperationPolicy::default());
+        assert_eq!(policy.timeout, None);
+    }
+
+    #[test]
+    fn policy_from_java_rejects_negatives() {
+        assert!(policy_from_java(-1, 0, 0).is_err());
+        assert!(policy_from_java(0, -1, 0).is_err());
+        assert!(policy_from_java(0, 0, -1).is_err());
+    }
+}
@@ -0,0 +1,221 @@
+use std::sync::Arc;
+
+use jni::JNIEnv;
+use jni::objects::JObject;
+use jni::sys::jlong;
+use nfscrs::nfs4_types::NFSStat4;
+use nfscrs::nfscrs_error::NFSCRSError;
+use nfscrs::{NFSClientSession, OpenedFile, SeekContent};
+
+use crate::error::{NfscrsJniError, handle_error, is_not_supported};
+use crate::opened_file::{FileHandle, file_handle};
+use crate::retry::call_with_deadline;
+use crate::session::{SessionHandle, session_handle};
+
+/// Returns the offset of the next data at or after `offset`, or -1 when only
+/// holes remain before end of file (`SEEK_DATA`).
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_fileSeekData(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    opened_file: jlong,
+    offset: jlong,
+) -> jlong {
+    let session_handle = unsafe { session_handle(session) };
+    let file_handle = unsafe { file_handle(opened_file) };
+    match seek_file(
+        &mut env,
+        session_handle,
+        &file_handle,
+        offset,
+        SeekContent::Data,
+    ) {
+        Ok(r) => r,
+        Err(e) => {
+            handle_error(&mut env, &e);
+            return -1;
+        }
+    }
+}
+
+/// Returns the offset of the next hole at or after `offset`; end of file
+/// counts as a hole (`SEEK_HOLE`).
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_fileSeekHole(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    opened_file: jlong,
+    offset: jlong,
+) -> jlong {
+    let session_handle = unsafe { session_handle(session) };
+    let file_handle = unsafe { file_handle(opened_file) };
+    match seek_file(
+        &mut env,
+        session_handle,
+        &file_handle,
+        offset,
+        SeekContent::Hole,
+    ) {
+        Ok(r) => r,
+        Err(e) => {
+            handle_error(&mut env, &e);
+            return -1;
+        }
+    }
+}
+
+/// Runs under a deadline rather than interruptibly, since it flushes
+/// buffered writes first.
+fn seek_file(
+    env: &mut JNIEnv,
+    session_handle: &'static SessionHandle,
+    file_handle: &Arc<FileHandle>,
+    offset: jlong,
+    what: SeekContent,
+) -> Result<jlong, NfscrsJniError> {
+    check_offset_length(offset, 0)?;
+    let file_handle = file_handle.clone();
+    call_with_deadline(env, session_handle, "fileSeek", move |session_ref| {
+        let mut opened_file_ref = file_handle.lock();
+        tracing::debug!("seek_file: {:?} {offset} {what:?}", opened_file_ref.path);
+        file_handle
+            .write_behind
+            .flush(session_ref, &mut opened_file_ref)?;
+        match session_ref.seek(&mut opened_file_ref, offset as u64, what) {
+            Ok(r) => Ok(r.offset as jlong),
+            Err(NFSCRSError::NFSStatError(NFSStat4::NFS4ERR_NXIO)) => Ok(-1),
+            Err(e) => Err(e.into()),
+        }
+    })
+}
+
+/// Preallocates `[offset, offset + length)` (ALLOCATE).
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_fileAllocate(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    opened_file: jlong,
+    offset: jlong,
+    length: jlong,
+) {
+    let session_handle = unsafe { session_handle(session) };
+    let file_handle = unsafe { file_handle(opened_file) };
+    match allocate_file(&mut env, session_handle, &file_handle, offset, length) {
+        Ok(_) => {}
+        Err(e) => {
+            handle_error(&mut env, &e);
+        }
+    }
+}
+
+fn allocate_file(
+    env: &mut JNIEnv,
+    session_handle: &'static SessionHandle,
+    file_handle: &Arc<FileHandle>,
+    offset: jlong,
+    length: jlong,
+) -> Result<(), NfscrsJniError> {
+    check_offset_length(offset, length)?;
+    let file_handle = file_handle.clone();
+    call_with_deadline(env, session_handle, "fileAllocate", move |session_ref| {
+        let mut opened_file_ref = file_handle.lock();
+        tracing::debug!(
+            "allocate_file: {:?} {offset}+{length}",
+            opened_file_ref.path
+        );
+        session_ref.allocate(&mut opened_file_ref, offset as u64, length as u64)?;
+        tracing::debug!("allocate_file ok : {:?}", opened_file_ref.path);
+        Ok(())
+    })
+}
+
+/// Punches a hole over `[offset, offset + length)` (DEALLOCATE).
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_fileDeallocate(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    opened_file: jlong,
+    offset: jlong,
+    length: jlong,
+) {
+    let session_handle = unsafe { session_handle(session) };
+    let file_handle = unsafe { file_handle(opened_file) };
+    match deallocate_file(&mut env, session_handle, &file_handle, offset, length) {
+        Ok(_) => {}
+        Err(e) => {
+            handle_error(&mut env, &e);
+        }
+    }
+}
+
+fn deallocate_file(
+    env: &mut JNIEnv,
+    session_handle: &'static SessionHandle,
+    file_handle: &Arc<FileHandle>,
+    offset: jlong,
+    length: jlong,
+) -> Result<(), NfscrsJniError> {
+    check_offset_length(offset, length)?;
+    file_handle.read_ahead.invalidate();
+    let file_handle = file_handle.clone();
+    call_with_deadline(env, session_handle, "fileDeallocate", move |session_ref| {
+        let mut opened_file_ref = file_handle.lock();
+        tracing::debug!(
+            "deallocate_file: {:?} {offset}+{length}",
+            opened_file_ref.path
+        );
+        file_handle
+            .write_behind
+            .flush(session_ref, &mut opened_file_ref)?;
+        session_ref.deallocate(&mut opened_file_ref, offset as u64, length as u64)?;
+        tracing::debug!("deallocate_file ok : {:?}", opened_file_ref.path);
+        Ok(())
+    })
+}
+
+fn check_offset_length(offset: jlong, length: jlong) -> Result<(), NfscrsJniError> {
+    if offset < 0 || length < 0 {
+        return Err(NfscrsJniError::IllegalArgument(format!(
+            "invalid range: offset {offset}, length {length}"
+        )));
+    }
+    Ok(())
+}
+
+/// Finds the next data segment `[start, end)` at or after `offset` and
+/// before `size`, or `None` when only holes remain. Servers without SEEK
+/// report everything up to `size` as data.
+pub fn next_data_segment(
+    session_ref: &mut NFSClientSession,
+    opened_file_ref: &mut OpenedFile,
+    offset: u64,
+    size: u64,
+) -> Result<Option<(u64, u64)>, NFSCRSError> {
+    if offset >= size {
+        return Ok(None);
+    }
+    let start = match session_ref.seek(opened_file_ref, offset, SeekContent::Data) {
+        Ok(r) => r.offset,
+        Err(NFSCRSError::NFSStatError(NFSStat4::NFS4ERR_NXIO)) => return Ok(None),
+        Err(e) if is_not_supported(&e) => return Ok(Some((offset, size))),
+        Err(e) => return Err(e),
+    };
+    if start >= size {
+        return Ok(None);
+    }
+    let end = match session_ref.seek(opened_file_ref, start, SeekContent::Hole) {
+        Ok(r) => r.offset.min(size),
+        Err(NFSCRSError::NFSStatError(NFSStat4::NFS4ERR_NXIO)) => size,
+        Err(e) if is_not_supported(&e) => size,
+        Err(e) => return Err(e),
+    };
+    Ok(Some((start, end)))
+}
@@ -0,0 +1,788 @@
+use std::fs::File;
+use std::io::{Seek, SeekFrom};
+use std::os::fd::{BorrowedFd, RawFd};
+use std::os::unix::fs::FileExt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, Sender, SyncSender, channel, sync_channel};
+use std::thread::JoinHandle;
+
+use jni::JNIEnv;
+use jni::objects::{JObject, JValue};
+use jni::sys::{jint, jlong};
+use nfscrs::nfscrs_error::NFSCRSError;
+use nfscrs::{NFSClientSession, OpenOptions, OpenedFile};
+
+use crate::attr_utils::get_file_size;
+use crate::basic_attr_bitmap;
+use crate::chunked_io::{read_chunked, write_chunked};
+use crate::error::{NfscrsJniError, handle_error};
+use crate::opened_file::{FileHandle, file_handle};
+use crate::retry::{OperationPolicy, retry, retry_idempotent};
+use crate::session::{SessionHandle, session_handle};
+use crate::sparse_ops::next_data_segment;
+
+/// Chunks queued between the NFS side and the local file side.
+const PIPELINE_DEPTH: usize = 4;
+
+/// Connections a large transfer runs on at once, the caller's session
+/// included.
+const TRANSFER_STREAMS: usize = 4;
+
+/// Chunks per stream below which a transfer stays on the caller's session,
+/// as connecting more sessions would cost more than it saves.
+const MIN_CHUNKS_PER_STREAM: usize = 4;
+
+/// Copies an opened NFS file into a local file descriptor.
+///
+/// `length` of -1 copies up to end of file. The local fd stays owned by the
+/// caller and is written from its offset 0 with `pwrite`; it is truncated
+/// first and ends up exactly as long as the number of bytes copied. `progress` may be
+/// null, otherwise it is an `NFS4ProgressCallback` whose
+/// `onProgress(long transferred, long total)` is called after every chunk.
+/// Returns the number of bytes copied.
+///
+/// Large transfers run on `TRANSFER_STREAMS` connections at once, as an
+/// `NFSClientSession` sends one compound at a time on its single slot: the
+/// caller's session and extra sessions to the same server, each with its
+/// own open of the file (see `SessionHandle::connect_stream`). Each stream
+/// takes the next maxread-sized chunk and the chunks are written at their
+/// offsets with `pwrite`, so progress is reported in completion order.
+/// Small transfers, and sessions made over a Java socket, which cannot
+/// connect again, stay on the caller's session, where a second thread
+/// overlaps the local writes with the next READ.
+///
+/// The fd must be seekable (a regular file or block device); pipes and
+/// sockets are rejected with `IllegalArgumentException`. Holes reported by
+/// SEEK are not transferred.
+///
+/// Progress callbacks run on the calling thread, so the session's timeout
+/// bounds each RPC rather than the whole call; each chunk is retried on its
+/// own. A failed chunk stops all streams.
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_downloadToFd(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    opened_file: jlong,
+    fd: jint,
+    offset: jlong,
+    length: jlong,
+    progress: JObject,
+) -> jlong {
+    let session_handle = unsafe { session_handle(session) };
+    let file_handle = unsafe { file_handle(opened_file) };
+    match download_to_fd(
+        session_handle,
+        &file_handle,
+        fd,
+        offset,
+        length,
+        &progress,
+        &mut env,
+    ) {
+        Ok(r) => r as jlong,
+        Err(e) => {
+            handle_error(&mut env, &e);
+            return -1;
+        }
+    }
+}
+
+fn download_to_fd(
+    session_handle: &'static SessionHandle,
+    file_handle: &Arc<FileHandle>,
+    fd: RawFd,
+    offset: jlong,
+    length: jlong,
+    progress: &JObject, // NFS4ProgressCallback
+    env: &mut JNIEnv,
+) -> Result<u64, NfscrsJniError> {
+    let (offset, length) = check_range(offset, length)?;
+    let local = dup_fd(fd)?;
+    let total = match length {
+        Some(length) => length as i64,
+        None => remote_size(session_handle, file_handle)? as i64 - offset as i64,
+    };
+    tracing::debug!("download_to_fd: offset {offset} total {total}");
+    // Holes are skipped rather than written, so they must not expose
+    // whatever the local file held before.
+    local.set_len(0)?;
+
+    if session_handle.can_connect_streams() {
+        let (max_read, segments) =
+            data_segments(session_handle, file_handle, offset, total.max(0) as u64)?;
+        let chunks = split_segments(&segments, max_read);
+        if chunks.len() >= TRANSFER_STREAMS * MIN_CHUNKS_PER_STREAM {
+            let local = Arc::new(local);
+            let done = run_streams(
+                session_handle,
+                file_handle,
+                chunks,
+                false,
+                progress,
+                total,
+                env,
+                {
+                    let local = local.clone();
+                    let policy = session_handle.policy();
+                    move |stream: &mut Stream, pos, len| {
+                        download_chunk(stream, &local, offset, pos, len, max_read, &policy)
+                    }
+                },
+            )?;
+            // Holes, and the range past an end of file met on the way, were
+            // not written.
+            let transferred = done.eof_at.unwrap_or(offset + total.max(0) as u64) - offset;
+            local.set_len(transferred)?;
+            tracing::debug!("download_to_fd ok : {transferred} bytes on parallel streams");
+            return Ok(transferred);
+        }
+    }
+
+    let (tx, rx) = sync_channel::<(u64, Vec<u8>)>(PIPELINE_DEPTH);
+    let writer = std::thread::spawn(move || -> std::io::Result<File> {
+        write_local(&local, rx)?;
+        Ok(local)
+    });
+    let result = download_loop(
+        session_handle,
+        file_handle,
+        offset,
+        total.max(0) as u64,
+        tx,
+        progress,
+        env,
+    );
+    let write_result = writer
+        .join()
+        .unwrap_or_else(|_| Err(std::io::Error::other("local writer thread panicked")));
+    let transferred = result?;
+    let local = write_result?;
+    // Holes at the end of the range were skipped, not written.
+    local.set_len(transferred)?;
+    tracing::debug!("download_to_fd ok : {transferred} bytes");
+    Ok(transferred)
+}
+
+/// Reads the data segments of `[offset, offset + total)` and queues them
+/// for the local writer; holes are skipped and read back as zeros from the
+/// truncated local file.
+fn download_loop(
+    session_handle: &SessionHandle,
+    file_handle: &FileHandle,
+    offset: u64,
+    total: u64,
+    tx: SyncSender<(u64, Vec<u8>)>,
+    progress: &JObject,
+    env: &mut JNIEnv,
+) -> Result<u64, NfscrsJniError> {
+    let policy = session_handle.policy();
+    let end = offset + total;
+    let mut pos = offset;
+    let mut segment_end = offset;
+    loop {
+        let step = retry_idempotent(&policy, "downloadToFd", || -> Result<_, NfscrsJniError> {
+            let mut session_ref = session_handle.lock();
+            let mut opened_file_ref = file_handle.lock();
+            file_handle
+                .write_behind
+                .flush(&mut session_ref, &mut opened_file_ref)?;
+            if pos >= segment_end {
+                match next_data_segment(&mut session_ref, &mut opened_file_ref, pos, end)? {
+                    Some((start, stop)) => {
+                        pos = start;
+                        segment_end = stop;
+                    }
+                    None => {
+                        pos = end;
+                        return Ok(None);
+                    }
+                }
+            }
+            let io_sizes = file_handle.io_sizes(&mut session_ref, &opened_file_ref)?;
+            let want = (segment_end - pos).min(io_sizes.max_read as u64) as usize;
+            let read_result = read_chunked(
+                &mut session_ref,
+                &mut opened_file_ref,
+                pos as usize,
+                want,
+                io_sizes.max_read,
+            )?;
+            Ok(Some((read_result.data, read_result.eof)))
+        })?;
+        let Some((chunk, eof)) = step else {
+            break;
+        };
+        let n = chunk.len() as u64;
+        if n > 0 && tx.send((pos - offset, chunk)).is_err() {
+            // The writer failed; its error is reported by the caller.
+            break;
+        }
+        pos += n;
+        report_progress(env, progress, pos - offset, total as i64)?;
+        if eof || n == 0 {
+            break;
+        }
+    }
+    Ok(pos - offset)
+}
+
+fn write_local(local: &File, rx: Receiver<(u64, Vec<u8>)>) -> std::io::Result<()> {
+    for (pos, data) in rx {
+        local.write_all_at(&data, pos)?;
+    }
+    Ok(())
+}
+
+/// Copies a local file descriptor into an opened NFS file.
+///
+/// `length` of -1 copies up to the local end of file as of the start of the
+/// call. The local fd is read from its offset 0 with `pread` and stays
+/// owned by the caller. Large transfers WRITE on several streams, and the
+/// fd must be seekable, as for `downloadToFd`. See there for `progress`
+/// and the return value.
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_uploadFromFd(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    opened_file: jlong,
+    fd: jint,
+    offset: jlong,
+    length: jlong,
+    progress: JObject,
+) -> jlong {
+    let session_handle = unsafe { session_handle(session) };
+    let file_handle = unsafe { file_handle(opened_file) };
+    match upload_from_fd(
+        session_handle,
+        &file_handle,
+        fd,
+        offset,
+        length,
+        &progress,
+        &mut env,
+    ) {
+        Ok(r) => r as jlong,
+        Err(e) => {
+            handle_error(&mut env, &e);
+            return -1;
+        }
+    }
+}
+
+fn upload_from_fd(
+    session_handle: &'static SessionHandle,
+    file_handle: &Arc<FileHandle>,
+    fd: RawFd,
+    offset: jlong,
+    length: jlong,
+    progress: &JObject, // NFS4ProgressCallback
+    env: &mut JNIEnv,
+) -> Result<u64, NfscrsJniError> {
+    let (offset, length) = check_range(offset, length)?;
+    let local = dup_fd(fd)?;
+    let total = match length {
+        Some(length) => length as i64,
+        None => local.metadata()?.len() as i64,
+    };
+    tracing::debug!("upload_from_fd: offset {offset} total {total}");
+
+    let chunk_size = retry(&session_handle.policy(), "uploadFromFd", || {
+        let mut session_ref = session_handle.lock();
+        let mut opened_file_ref = file_handle.lock();
+        file_handle
+            .write_behind
+            .flush(&mut session_ref, &mut opened_file_ref)?;
+        Ok::<_, NfscrsJniError>(
+            file_handle
+                .io_sizes(&mut session_ref, &opened_file_ref)?
+                .max_write,
+        )
+    })?;
+    file_handle.read_ahead.invalidate();
+
+    if session_handle.can_connect_streams() {
+        let chunks = split_segments(&[(0, total.max(0) as u64)], chunk_size);
+        if chunks.len() >= TRANSFER_STREAMS * MIN_CHUNKS_PER_STREAM {
+            let local = Arc::new(local);
+            let done = run_streams(
+                session_handle,
+                file_handle,
+                chunks,
+                true,
+                progress,
+                total,
+                env,
+                {
+                    let policy = session_handle.policy();
+                    move |stream: &mut Stream, pos, len| {
+                        upload_chunk(stream, &local, offset, pos, len, chunk_size, &policy)
+                    }
+                },
+            )?;
+            tracing::debug!(
+                "upload_from_fd ok : {} bytes on parallel streams",
+                done.bytes
+            );
+            return Ok(done.bytes);
+        }
+    }
+
+    let (tx, rx) = sync_channel::<std::io::Result<(u64, Vec<u8>)>>(PIPELINE_DEPTH);
+    let reader = std::thread::spawn(move || read_local(&local, length, chunk_size, tx));
+
+    let result = upload_loop(
+        session_handle,
+        file_handle,
+        offset,
+        chunk_size,
+        total,
+        &rx,
+        progress,
+        env,
+    );
+    // Unblocks the reader if we stopped early.
+    drop(rx);
+    let _ = reader.join();
+    let transferred = result?;
+    tracing::debug!("upload_from_fd ok : {transferred} bytes");
+    Ok(transferred)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn upload_loop(
+    session_handle: &SessionHandle,
+    file_handle: &FileHandle,
+    offset: u64,
+    chunk_size: usize,
+    total: i64,
+    rx: &Receiver<std::io::Result<(u64, Vec<u8>)>>,
+    progress: &JObject,
+    env: &mut JNIEnv,
+) -> Result<u64, NfscrsJniError> {
+    let policy = session_handle.policy();
+    let mut transferred: u64 = 0;
+    for chunk in rx {
+        let (pos, data) = chunk?;
+        retry(&policy, "uploadFromFd", || {
+            let mut session_ref = session_handle.lock();
+            let mut opened_file_ref = file_handle.lock();
+            write_chunked(
+                &mut session_ref,
+                &mut opened_file_ref,
+                (offset + pos) as usize,
+                &data,
+                chunk_size,
+            )
+        })?;
+        transferred += data.len() as u64;
+        report_progress(env, progress, transferred, total)?;
+    }
+    Ok(transferred)
+}
+
+fn read_local(
+    local: &File,
+    length: Option<u64>,
+    chunk_size: usize,
+    tx: SyncSender<std::io::Result<(u64, Vec<u8>)>>,
+) {
+    let mut pos: u64 = 0;
+    loop {
+        let want = match length {
+            Some(length) => (length - pos).min(chunk_size as u64) as usize,
+            None => chunk_size,
+        };
+        if want == 0 {
+            return;
+        }
+        let mut data = vec![0u8; want];
+        let mut filled = 0;
+        while filled < want {
+            match local.read_at(&mut data[filled..], pos + filled as u64) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    let _ = tx.send(Err(e));
+                    return;
+                }
+            }
+        }
+        data.truncate(filled);
+        if filled == 0 || tx.send(Ok((pos, data))).is_err() {
+            return;
+        }
+        pos += filled as u64;
+    }
+}
+
+/// One connection of a parallel transfer: the caller's session and opened
+/// file, or an extra session with its own open of the same file.
+enum Stream {
+    Caller {
+        session_handle: &'static SessionHandle,
+        file_handle: Arc<FileHandle>,
+    },
+    Extra {
+        session: NFSClientSession,
+        file: OpenedFile,
+    },
+}
+
+impl Stream {
+    fn connect(
+        session_handle: &SessionHandle,
+        file_handle: &FileHandle,
+        write: bool,
+    ) -> Result<Stream, NfscrsJniError> {
+        let path = file_handle.lock().path.clone();
+        let mut session = session_handle.connect_stream()?;
+        let options = if write {
+            OpenOptions::new().write(true)
+        } else {
+            OpenOptions::new().read(true)
+        };
+        let file = session.open_file(&path, options)?;
+        Ok(Stream::Extra { session, file })
+    }
+
+    /// Runs `op` on this stream, taking the caller's locks for the one call.
+    fn run<T>(
+        &mut self,
+        op: impl FnOnce(&mut NFSClientSession, &mut OpenedFile) -> Result<T, NFSCRSError>,
+    ) -> Result<T, NFSCRSError> {
+        match self {
+            Stream::Caller {
+                session_handle,
+                file_handle,
+            } => op(&mut *session_handle.lock(), &mut *file_handle.lock()),
+            Stream::Extra { session, file } => op(session, file),
+        }
+    }
+
+    fn close(self) {
+        if let Stream::Extra {
+            mut session,
+            mut file,
+        } = self
+            && let Err(e) = session.close(&mut file)
+        {
+            tracing::debug!("transfer stream: close failed: {e}");
+        }
+    }
+}
+
+/// A chunk done by a stream.
+struct ChunkDone {
+    /// Bytes moved.
+    bytes: u64,
+    /// Remote offset where end of file was met, if it was.
+    eof_at: Option<u64>,
+}
+
+/// Totals of `run_streams`.
+#[derive(Default)]
+struct StreamsDone {
+    bytes: u64,
+    /// Lowest remote offset where a stream met end of file.
+    eof_at: Option<u64>,
+}
+
+/// Runs `transfer_chunk(stream, pos, len)` for every chunk on up to
+/// `TRANSFER_STREAMS` streams, each taking the next chunk when done with
+/// its last, and reports progress from the calling thread. Extra streams
+/// that fail to connect are left out; the caller's stream always runs.
+/// Every stream retries under the policy of the caller's session.
+#[allow(clippy::too_many_arguments)]
+fn run_streams<F>(
+    session_handle: &'static SessionHandle,
+    file_handle: &Arc<FileHandle>,
+    chunks: Vec<(u64, usize)>,
+    write: bool,
+    progress: &JObject,
+    total: i64,
+    env: &mut JNIEnv,
+    transfer_chunk: F,
+) -> Result<StreamsDone, NfscrsJniError>
+where
+    F: Fn(&mut Stream, u64, usize) -> Result<ChunkDone, NfscrsJniError> + Send + Sync + 'static,
+{
+    let chunks = Arc::new(chunks);
+    let next = Arc::new(AtomicUsize::new(0));
+    let stop = Arc::new(AtomicBool::new(false));
+    let transfer_chunk = Arc::new(transfer_chunk);
+    let (tx, rx) = channel::<Result<ChunkDone, NfscrsJniError>>();
+    let workers: Vec<JoinHandle<()>> = (0..TRANSFER_STREAMS)
+        .map(|index| {
+            let file_handle = file_handle.clone();
+            let chunks = chunks.clone();
+            let next = next.clone();
+            let stop = stop.clone();
+            let transfer_chunk = transfer_chunk.clone();
+            let tx = tx.clone();
+            std::thread::spawn(move || {
+                let stream = if index == 0 {
+                    Stream::Caller {
+                        session_handle,
+                        file_handle,
+                    }
+                } else {
+                    match Stream::connect(session_handle, &file_handle, write) {
+                        Ok(stream) => stream,
+                        Err(e) => {
+                            tracing::debug!("transfer stream {index}: not connected: {e}");
+                            return;
+                        }
+                    }
+                };
+                stream_loop(stream, &chunks, &next, &stop, &*transfer_chunk, &tx);
+            })
+        })
+        .collect();
+    drop(tx);
+
+    let mut done = StreamsDone::default();
+    let mut result = Ok(());
+    for chunk in &rx {
+        let reported = chunk.and_then(|chunk| {
+            done.bytes += chunk.bytes;
+            if let Some(eof_at) = chunk.eof_at {
+                done.eof_at = Some(done.eof_at.map_or(eof_at, |e| e.min(eof_at)));
+            }
+            report_progress(env, progress, done.bytes, total)
+        });
+        if let Err(e) = reported {
+            result = Err(e);
+            break;
+        }
+    }
+    stop.store(true, Ordering::Relaxed);
+    for worker in workers {
+        if worker.join().is_err() {
+            result = result.and(Err(NfscrsJniError::NFSCRSJNIError(
+                "transfer stream panicked".to_string(),
+            )));
+        }
+    }
+    result.map(|()| done)
+}
+
+fn stream_loop(
+    mut stream: Stream,
+    chunks: &[(u64, usize)],
+    next: &AtomicUsize,
+    stop: &AtomicBool,
+    transfer_chunk: &dyn Fn(&mut Stream, u64, usize) -> Result<ChunkDone, NfscrsJniError>,
+    tx: &Sender<Result<ChunkDone, NfscrsJniError>>,
+) {
+    while !stop.load(Ordering::Relaxed) {
+        let Some(&(pos, len)) = chunks.get(next.fetch_add(1, Ordering::Relaxed)) else {
+            break;
+        };
+        let result = transfer_chunk(&mut stream, pos, len);
+        let failed = result.is_err();
+        if tx.send(result).is_err() || failed {
+            stop.store(true, Ordering::Relaxed);
+            break;
+        }
+    }
+    stream.close();
+}
+
+/// READs `len` bytes at remote offset `pos` and writes them to `local` at
+/// `pos - offset`.
+fn download_chunk(
+    stream: &mut Stream,
+    local: &File,
+    offset: u64,
+    pos: u64,
+    len: usize,
+    max_read: usize,
+    policy: &OperationPolicy,
+) -> Result<ChunkDone, NfscrsJniError> {
+    let read_result = retry_idempotent(policy, "downloadToFd", || {
+        stream.run(|session_ref, opened_file_ref| {
+            read_chunked(session_ref, opened_file_ref, pos as usize, len, max_read)
+        })
+    })?;
+    local.write_all_at(&read_result.data, pos - offset)?;
+    let bytes = read_result.data.len() as u64;
+    Ok(ChunkDone {
+        bytes,
+        eof_at: read_result.eof.then_some(pos + bytes),
+    })
+}
+
+/// Reads `len` bytes of `local` at `pos` and WRITEs them at remote offset
+/// `offset + pos`. A local file that shrank gives a short or empty chunk.
+fn upload_chunk(
+    stream: &mut Stream,
+    local: &File,
+    offset: u64,
+    pos: u64,
+    len: usize,
+    max_write: usize,
+    policy: &OperationPolicy,
+) -> Result<ChunkDone, NfscrsJniError> {
+    let mut data = vec![0u8; len];
+    let mut filled = 0;
+    while filled < len {
+        match local.read_at(&mut data[filled..], pos + filled as u64) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    data.truncate(filled);
+    if !data.is_empty() {
+        retry(policy, "uploadFromFd", || {
+            stream.run(|session_ref, opened_file_ref| {
+                write_chunked(
+                    session_ref,
+                    opened_file_ref,
+                    (offset + pos) as usize,
+                    &data,
+                    max_write,
+                )
+            })
+        })?;
+    }
+    Ok(ChunkDone {
+        bytes: filled as u64,
+        eof_at: None,
+    })
+}
+
+/// Flushes write-behind data and returns the maxread of the file with the
+/// data segments of `[offset, offset + total)`.
+fn data_segments(
+    session_handle: &SessionHandle,
+    file_handle: &FileHandle,
+    offset: u64,
+    total: u64,
+) -> Result<(usize, Vec<(u64, u64)>), NfscrsJniError> {
+    let end = offset + total;
+    retry_idempotent(&session_handle.policy(), "downloadToFd", || {
+        let mut session_ref = session_handle.lock();
+        let mut opened_file_ref = file_handle.lock();
+        file_handle
+            .write_behind
+            .flush(&mut session_ref, &mut opened_file_ref)?;
+        let io_sizes = file_handle.io_sizes(&mut session_ref, &opened_file_ref)?;
+        let mut segments = Vec::new();
+        let mut pos = offset;
+        while let Some((start, stop)) =
+            next_data_segment(&mut session_ref, &mut opened_file_ref, pos, end)?
+        {
+            segments.push((start, stop));
+            if stop <= pos {
+                break;
+            }
+            pos = stop;
+        }
+        Ok::<_, NfscrsJniError>((io_sizes.max_read, segments))
+    })
+}
+
+/// Cuts `[start, stop)` segments into chunks of at most `chunk_size` bytes.
+fn split_segments(segments: &[(u64, u64)], chunk_size: usize) -> Vec<(u64, usize)> {
+    let mut chunks = Vec::new();
+    for &(start, stop) in segments {
+        let mut pos = start;
+        while pos < stop {
+            let len = (stop - pos).min(chunk_size as u64) as usize;
+            chunks.push((pos, len));
+            pos += len as u64;
+        }
+    }
+    chunks
+}
+
+/// Validates a Java `(offset, length)` pair; a negative length means "to
+/// end of file".
+fn check_range(offset: jlong, length: jlong) -> Result<(u64, Option<u64>), NfscrsJniError> {
+    if offset < 0 {
+        return Err(NfscrsJniError::IllegalArgument(format!(
+            "negative offset: {offset}"
+        )));
+    }
+    let length = if length < 0 {
+        None
+    } else {
+        Some(length as u64)
+    };
+    Ok((offset as u64, length))
+}
+
+/// Duplicates a caller-owned fd so it can be used from a native thread and
+/// closed independently, after checking that it supports positioned I/O.
+fn dup_fd(fd: RawFd) -> Result<File, NfscrsJniError> {
+    if fd < 0 {
+        return Err(NfscrsJniError::IllegalArgument(format!(
+            "invalid file descriptor: {fd}"
+        )));
+    }
+    let owned = unsafe { BorrowedFd::borrow_raw(fd) }.try_clone_to_owned()?;
+    let mut file = File::from(owned);
+    // pread/pwrite fail with ESPIPE on pipes and sockets; lseek tells up
+    // front.
+    if let Err(e) = file.seek(SeekFrom::Current(0)) {
+        return Err(NfscrsJniError::IllegalArgument(format!(
+            "file descriptor {fd} is not seekable: {e}"
+        )));
+    }
+    Ok(file)
+}
+
+fn remote_size(
+    session_handle: &SessionHandle,
+    file_handle: &FileHandle,
+) -> Result<u64, NfscrsJniError> {
+    retry_idempotent(&session_handle.policy(), "downloadToFd", || {
+        let mut session_ref = session_handle.lock();
+        let opened_file_ref = file_handle.lock();
+        let fattr4 = session_ref.get_attr(&opened_file_ref.path, basic_attr_bitmap())?;
+        get_file_size(&fattr4)
+    })
+}
+
+/// Calls `onProgress(transferred, total)` on a non-null
+/// `NFS4ProgressCallback`.
+pub fn report_progress(
+    env: &mut JNIEnv,
+    progress: &JObject,
+    transferred: u64,
+    total: i64,
+) -> Result<(), NfscrsJniError> {
+    if progress.is_null() {
+        return Ok(());
+    }
+    env.call_method(
+        progress,
+        "onProgress",
+        "(JJ)V",
+        &[JValue::Long(transferred as i64), JValue::Long(total)],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_segments_cuts_each_segment() {
+        assert_eq!(
+            split_segments(&[(0, 10), (20, 25)], 4),
+            vec![(0, 4), (4, 4), (8, 2), (20, 4), (24, 1)]
+        );
+        assert!(split_segments(&[], 4).is_empty());
+        assert!(split_segments(&[(5, 5)], 4).is_empty());
+    }
+}
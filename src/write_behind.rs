@@ -0,0 +1,218 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use nfscrs::OpenedFile;
+use nfscrs::nfscrs_error::NFSCRSError;
+
+use crate::chunked_io::write_chunked;
+use crate::opened_file::FileHandle;
+use crate::session::{SessionGuard, SessionHandle};
+
+#[derive(Debug, Clone, Copy)]
+pub struct WriteBehindConfig {
+    /// Size of each WRITE sent to the server, normally its maxwrite.
+    pub chunk_size: usize,
+    /// Buffered bytes that trigger a flush.
+    pub threshold: usize,
+    /// Idle time after the last write before the buffer is flushed. Zero
+    /// disables the timer.
+    pub flush_delay: Duration,
+}
+
+#[derive(Default)]
+struct WriteBehindState {
+    config: Option<WriteBehindConfig>,
+    /// File offset of `buf[0]`.
+    offset: usize,
+    buf: Vec<u8>,
+    last_write: Option<Instant>,
+    timer_running: bool,
+    /// Failure of a deferred write, reported on the next call.
+    error: Option<NFSCRSError>,
+}
+
+/// Optional write-behind buffer attached to each opened file.
+///
+/// Adjacent small writes are coalesced in memory and sent in chunks. All
+/// methods must be called with the session and file locks held, which keeps
+/// deferred writes ordered with direct ones.
+#[derive(Default)]
+pub struct WriteBehind {
+    state: Mutex<WriteBehindState>,
+}
+
+impl WriteBehind {
+    fn lock(&self) -> MutexGuard<'_, WriteBehindState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Enables or disables buffering, flushing anything already buffered.
+    pub fn set_config(
+        &self,
+        session_ref: &mut SessionGuard,
+        opened_file_ref: &mut OpenedFile,
+        config: Option<WriteBehindConfig>,
+    ) -> Result<(), NFSCRSError> {
+        self.flush(session_ref, opened_file_ref)?;
+        self.lock().config = config;
+        Ok(())
+    }
+
+    /// Returns the error of a failed deferred write, if any.
+    pub fn take_error(&self) -> Result<(), NFSCRSError> {
+        match self.lock().error.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Buffers `data` at `offset` when it is small enough. Returns `false`
+    /// when the caller should write it directly instead; any pending data
+    /// has then already been flushed.
+    pub fn buffer(
+        &self,
+        session_ref: &mut SessionGuard,
+        opened_file_ref: &mut OpenedFile,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<bool, NFSCRSError> {
+        self.take_error()?;
+        let mut state = self.lock();
+        let Some(config) = state.config else {
+            return Ok(false);
+        };
+        let adjacent = state.buf.is_empty() || state.offset + state.buf.len() == offset;
+        if !adjacent || data.len() >= config.chunk_size {
+            flush_state(&mut state, session_ref, opened_file_ref)?;
+            if data.len() >= config.chunk_size {
+                return Ok(false);
+            }
+        }
+
+        if state.buf.is_empty() {
+            state.offset = offset;
+        }
+        state.buf.extend_from_slice(data);
+        state.last_write = Some(Instant::now());
+        if state.buf.len() >= config.threshold {
+            flush_state(&mut state, session_ref, opened_file_ref)?;
+        }
+        Ok(true)
+    }
+
+    /// Sends all buffered data to the server.
+    pub fn flush(
+        &self,
+        session_ref: &mut SessionGuard,
+        opened_file_ref: &mut OpenedFile,
+    ) -> Result<(), NFSCRSError> {
+        let mut state = self.lock();
+        flush_state(&mut state, session_ref, opened_file_ref)?;
+        match state.error.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Like `flush`, for background threads that have no caller to report
+    /// to: a failure is kept and thrown by the next call on the file, and a
+    /// failure already kept is left for it. Returns whether nothing is left
+    /// buffered.
+    pub fn flush_deferred(
+        &self,
+        session_ref: &mut SessionGuard,
+        opened_file_ref: &mut OpenedFile,
+    ) -> bool {
+        let mut state = self.lock();
+        match flush_state(&mut state, session_ref, opened_file_ref) {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::debug!("write-behind flush failed: {e}");
+                state.error.get_or_insert(e);
+                false
+            }
+        }
+    }
+
+    /// Claims the flush timer. Returns the delay when the caller should
+    /// start one.
+    pub fn claim_timer(&self) -> Option<Duration> {
+        let mut state = self.lock();
+        let delay = state.config?.flush_delay;
+        if delay.is_zero() || state.timer_running || state.buf.is_empty() {
+            return None;
+        }
+        state.timer_running = true;
+        Some(delay)
+    }
+
+    /// Flushes from the timer thread once the file has been idle for
+    /// `delay`. Returns `true` when the timer should keep waiting.
+    fn timer_tick(
+        &self,
+        session_ref: &mut SessionGuard,
+        opened_file_ref: &mut OpenedFile,
+        delay: Duration,
+    ) -> bool {
+        let mut state = self.lock();
+        if !state.buf.is_empty() && state.last_write.is_some_and(|t| t.elapsed() < delay) {
+            return true;
+        }
+        if let Err(e) = flush_state(&mut state, session_ref, opened_file_ref) {
+            tracing::debug!("write-behind flush failed: {e}");
+            state.error = Some(e);
+        }
+        state.timer_running = false;
+        false
+    }
+}
+
+/// Sends the buffer in chunks. Only the chunks the server accepted are
+/// dropped, so after a failure the rest stays buffered for the next flush.
+fn flush_state(
+    state: &mut WriteBehindState,
+    session_ref: &mut SessionGuard,
+    opened_file_ref: &mut OpenedFile,
+) -> Result<(), NFSCRSError> {
+    let chunk_size = state.config.map_or(state.buf.len(), |c| c.chunk_size);
+    let mut sent = 0;
+    let result = loop {
+        if sent == state.buf.len() {
+            break Ok(());
+        }
+        let end = state.buf.len().min(sent + chunk_size);
+        if let Err(e) = write_chunked(
+            session_ref,
+            opened_file_ref,
+            state.offset + sent,
+            &state.buf[sent..end],
+            chunk_size,
+        ) {
+            break Err(e);
+        }
+        sent = end;
+    };
+    state.buf.drain(..sent);
+    state.offset += sent;
+    result
+}
+
+pub fn spawn_flush_timer(
+    session_handle: &'static SessionHandle,
+    file_handle: Arc<FileHandle>,
+    delay: Duration,
+) {
+    std::thread::spawn(move || {
+        loop {
+            std::thread::sleep(delay);
+            let mut session_ref = session_handle.lock();
+            let mut opened_file_ref = file_handle.lock();
+            if !file_handle
+                .write_behind
+                .timer_tick(&mut session_ref, &mut opened_file_ref, delay)
+            {
+                break;
+            }
+        }
+    });
+}
@@ -0,0 +1,262 @@
+use jni::JNIEnv;
+use jni::objects::{JByteArray, JObject, JString, JValue};
+use jni::sys::{jboolean, jbyteArray, jint, jlong, jobject};
+use nfscrs::SetXattrOption;
+use nfscrs::fattr4::{FAttr4Type, fattr4_names, set_bitmap};
+use nfscrs::nfs4_types::{BitMap4, NFSStat4};
+use nfscrs::nfscrs_error::NFSCRSError;
+
+use crate::error::{NfscrsJniError, handle_error, is_not_supported};
+use crate::jni_utils::{as_class, jni_cache};
+use crate::retry::{call_interruptible, call_with_deadline};
+use crate::session::{SessionHandle, session_handle};
+
+pub const XATTR_SET_EITHER: jint = 0;
+pub const XATTR_SET_CREATE: jint = 1;
+pub const XATTR_SET_REPLACE: jint = 2;
+
+/// Maps xattr failures to the exceptions `UserDefinedFileAttributeView`
+/// callers expect.
+fn xattr_error(err: NFSCRSError, name: &str) -> NfscrsJniError {
+    match err {
+        e if is_not_supported(&e) => NfscrsJniError::UnsupportedOperation(
+            "server does not support extended attributes".to_string(),
+        ),
+        NFSCRSError::NFSStatError(NFSStat4::NFS4ERR_NOXATTR) => {
+            NfscrsJniError::FileSystemError(format!("{name}: no such attribute"))
+        }
+        NFSCRSError::NFSStatError(NFSStat4::NFS4ERR_EXIST) => {
+            NfscrsJniError::FileSystemError(format!("{name}: attribute exists"))
+        }
+        NFSCRSError::NFSStatError(NFSStat4::NFS4ERR_XATTR2BIG) => {
+            NfscrsJniError::FileSystemError(format!("{name}: attribute value too large"))
+        }
+        e => e.into(),
+    }
+}
+
+/// Whether the file system holding `path` supports extended attributes
+/// (the `xattr_support` attribute).
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_xattrSupported(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    path: JString,
+) -> jboolean {
+    let session_handle = unsafe { session_handle(session) };
+    match xattr_supported(session_handle, &mut env, &path) {
+        Ok(r) => r as jboolean,
+        Err(e) => {
+            handle_error(&mut env, &e);
+            return 0;
+        }
+    }
+}
+
+fn xattr_supported(
+    session_handle: &'static SessionHandle,
+    env: &mut JNIEnv,
+    path: &JString,
+) -> Result<bool, NfscrsJniError> {
+    let path_str: String = env.get_string(path)?.into();
+    call_interruptible(env, session_handle, "xattrSupported", move |session_ref| {
+        let abs_path = session_ref.resolve(path_str.clone())?;
+        let mut bitmap = BitMap4::new();
+        set_bitmap(&mut bitmap, fattr4_names::FATTR4_XATTR_SUPPORT);
+        let fattr4 = session_ref.get_attr(&abs_path, bitmap)?;
+        match fattr4.fetch_attr(fattr4_names::FATTR4_XATTR_SUPPORT) {
+            Ok(FAttr4Type::FATTR4_XATTR_SUPPORT(supported)) => Ok(supported),
+            _ => Ok(false),
+        }
+    })
+}
+
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_getXattr(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    path: JString,
+    name: JString,
+) -> jbyteArray {
+    let session_handle = unsafe { session_handle(session) };
+    match get_xattr(session_handle, &mut env, &path, &name) {
+        Ok(r) => r,
+        Err(e) => {
+            handle_error(&mut env, &e);
+            return std::ptr::null_mut();
+        }
+    }
+}
+
+fn get_xattr(
+    session_handle: &'static SessionHandle,
+    env: &mut JNIEnv,
+    path: &JString,
+    name: &JString,
+) -> Result<jbyteArray, NfscrsJniError> {
+    let path_str: String = env.get_string(path)?.into();
+    let name: String = env.get_string(name)?.into();
+    let value = call_interruptible(env, session_handle, "getXattr", move |session_ref| {
+        let abs_path = session_ref.resolve(path_str.clone())?;
+        tracing::debug!("get_xattr: {:?} {name}", abs_path);
+        let value = session_ref
+            .get_xattr(&abs_path, &name)
+            .map_err(|e| xattr_error(e, &name))?;
+        tracing::debug!("get_xattr ok : {:?} {name}", abs_path);
+        Ok(value)
+    })?;
+    let byte_array = env.byte_array_from_slice(&value)?;
+    Ok(byte_array.into_raw())
+}
+
+/// Sets `name` to `value`. `option` is one of `XATTR_SET_EITHER`,
+/// `XATTR_SET_CREATE` or `XATTR_SET_REPLACE`.
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_setXattr(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    path: JString,
+    name: JString,
+    value: JByteArray,
+    option: jint,
+) {
+    let session_handle = unsafe { session_handle(session) };
+    match set_xattr(session_handle, &mut env, &path, &name, &value, option) {
+        Ok(_) => {}
+        Err(e) => {
+            handle_error(&mut env, &e);
+        }
+    }
+}
+
+fn set_xattr(
+    session_handle: &'static SessionHandle,
+    env: &mut JNIEnv,
+    path: &JString,
+    name: &JString,
+    value: &JByteArray,
+    option: jint,
+) -> Result<(), NfscrsJniError> {
+    let option = match option {
+        XATTR_SET_EITHER => SetXattrOption::Either,
+        XATTR_SET_CREATE => SetXattrOption::Create,
+        XATTR_SET_REPLACE => SetXattrOption::Replace,
+        _ => {
+            return Err(NfscrsJniError::IllegalArgument(format!(
+                "invalid setxattr option: {option}"
+            )));
+        }
+    };
+    let path_str: String = env.get_string(path)?.into();
+    let name: String = env.get_string(name)?.into();
+    let value = env.convert_byte_array(value)?;
+    call_with_deadline(env, session_handle, "setXattr", move |session_ref| {
+        let abs_path = session_ref.resolve(path_str.clone())?;
+        tracing::debug!("set_xattr: {:?} {name}", abs_path);
+        session_ref
+            .set_xattr(&abs_path, &name, &value, option)
+            .map_err(|e| xattr_error(e, &name))?;
+        tracing::debug!("set_xattr ok : {:?} {name}", abs_path);
+        Ok(())
+    })
+}
+
+/// Returns the attribute names of `path` as a `List<String>`.
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_listXattrs(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    path: JString,
+) -> jobject {
+    let session_handle = unsafe { session_handle(session) };
+    match list_xattrs(session_handle, &mut env, &path) {
+        Ok(r) => r,
+        Err(e) => {
+            handle_error(&mut env, &e);
+            return std::ptr::null_mut();
+        }
+    }
+}
+
+fn list_xattrs(
+    session_handle: &'static SessionHandle,
+    env: &mut JNIEnv,
+    path: &JString,
+) -> Result<jobject, NfscrsJniError> {
+    let path_str: String = env.get_string(path)?.into();
+    let names = call_interruptible(env, session_handle, "listXattrs", move |session_ref| {
+        let abs_path = session_ref.resolve(path_str.clone())?;
+        tracing::debug!("list_xattrs: {:?}", abs_path);
+        session_ref
+            .list_xattrs(&abs_path)
+            .map_err(|e| xattr_error(e, ""))
+    })?;
+
+    let cache = jni_cache()?;
+    let array_list_obj = unsafe {
+        env.new_object_unchecked(
+            as_class(&cache.array_list_class),
+            cache.array_list_ctor,
+            &[],
+        )
+    }?;
+    for name in names {
+        let jname = env.new_string(name)?;
+        unsafe {
+            env.call_method_unchecked(
+                &array_list_obj,
+                cache.array_list_add,
+                jni::signature::ReturnType::Primitive(jni::signature::Primitive::Boolean),
+                &[JValue::Object(&jname).as_jni()],
+            )
+        }?;
+        env.delete_local_ref(jname)?;
+    }
+    tracing::debug!("list_xattrs ok : {:?}", path_str);
+    Ok(array_list_obj.into_raw())
+}
+
+#[allow(non_snake_case)]
+#[unsafe(no_mangle)]
+pub extern "system" fn Java_com_algebnaly_nfs4c_NFS4CNativeBridge_removeXattr(
+    mut env: JNIEnv,
+    _this: JObject,
+    session: jlong,
+    path: JString,
+    name: JString,
+) {
+    let session_handle = unsafe { session_handle(session) };
+    match remove_xattr(session_handle, &mut env, &path, &name) {
+        Ok(_) => {}
+        Err(e) => {
+            handle_error(&mut env, &e);
+        }
+    }
+}
+
+fn remove_xattr(
+    session_handle: &'static SessionHandle,
+    env: &mut JNIEnv,
+    path: &JString,
+    name: &JString,
+) -> Result<(), NfscrsJniError> {
+    let path_str: String = env.get_string(path)?.into();
+    let name: String = env.get_string(name)?.into();
+    call_with_deadline(env, session_handle, "removeXattr", move |session_ref| {
+        let abs_path = session_ref.resolve(path_str.clone())?;
+        tracing::debug!("remove_xattr: {:?} {name}", abs_path);
+        session_ref
+            .remove_xattr(&abs_path, &name)
+            .map_err(|e| xattr_error(e, &name))?;
+        tracing::debug!("remove_xattr ok : {:?} {name}", abs_path);
+        Ok(())
+    })
+}